@@ -0,0 +1,60 @@
+//! Times the translation of large outputs to Windows paths, like of a long
+//! `git log -p` or `git ls-files`. Run with `cargo bench`.
+
+extern crate wslgit_for_jetbrains;
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use wslgit_for_jetbrains::Translator;
+
+/// Lines like the ones of `git log -p`, mostly without any Linux path.
+fn log_output(commits: usize) -> Vec<String> {
+    let mut lines = vec![];
+    for i in 0..commits {
+        lines.push(format!("commit {:040x}", i));
+        lines.push(String::from("Author: Some One <some.one@example.com>"));
+        lines.push(String::from("Date:   Mon Jan 1 12:00:00 2024 +0100"));
+        lines.push(String::new());
+        lines.push(format!("    Change number {} of the thing", i));
+        lines.push(String::new());
+        lines.push(format!("diff --git a/src/file{}.rs b/src/file{}.rs", i, i));
+        lines.push(String::from("@@ -1,3 +1,3 @@"));
+        lines.push(String::from("-    let x = 1;"));
+        lines.push(String::from("+    let x = 2;"));
+    }
+    lines
+}
+
+/// Lines like the ones of `git ls-files` with `--full-name` paths below `/mnt`.
+fn ls_files_output(files: usize) -> Vec<String> {
+    (0..files).map(|i| format!("/mnt/c/repos/thing/src/module{}/file{}.rs", i % 50, i)).collect()
+}
+
+fn time<F: FnMut(String) -> String>(name: &str, lines: &[String], mut translate: F) {
+    let start = Instant::now();
+    let mut bytes = 0;
+    for line in lines {
+        bytes += translate(line.clone()).len();
+    }
+    let elapsed = start.elapsed();
+    println!("{:<40} {:>8} lines {:>10.3} ms {:>8} ns/line ({} bytes)",
+             name, lines.len(), as_millis(elapsed), elapsed.as_nanos() / lines.len() as u128, bytes);
+}
+
+fn as_millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+fn main() {
+    let log = log_output(50_000);
+    let ls_files = ls_files_output(200_000);
+    let translator = Translator::default();
+    let unc_translator = Translator::new("/mnt", HashMap::new(), Some(String::from("Ubuntu")));
+
+    time("Translator::to_win_mounted, git log -p", &log, |line| translator.to_win_mounted(line));
+    time("Translator::to_win_mounted, git ls-files", &ls_files, |line| translator.to_win_mounted(line));
+    time("Translator::to_win, git log -p", &log, |line| translator.to_win(line));
+    time("Translator::to_win, git ls-files", &ls_files, |line| translator.to_win(line));
+    time("Translator::to_win with UNC, git log -p", &log, |line| unc_translator.to_win(line));
+}
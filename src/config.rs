@@ -0,0 +1,274 @@
+//! Settings from a `.wslgit` file and the global configuration file, written
+//! in a subset of TOML.
+
+use std::collections::HashMap;
+use std::env;
+use std::ffi::OsString;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Name of the configuration file, searched from the working directory upwards.
+pub const CONFIG_FILE_NAME: &str = ".wslgit";
+
+/// The settings of a configuration file, by the name of the environment
+/// variable they correspond to, e.g. `WSLGIT_DISTRO` for `distro`.
+pub type Config = HashMap<String, String>;
+
+/// The settings which a `.wslgit` file may change. It comes with the
+/// repository, so settings choosing the programs run, their arguments, the
+/// working directory or the forwarded variables are only read from the
+/// environment and the global configuration file.
+const REPOSITORY_SETTINGS: &[&str] = &[
+    "WSLGIT_CD", "WSLGIT_COLOR", "WSLGIT_DEBUG", "WSLGIT_DRIVE_CASE", "WSLGIT_EMBEDDED_PATHS",
+    "WSLGIT_EXPAND_VARS", "WSLGIT_JSON_OUTPUT", "WSLGIT_MAP_HOME", "WSLGIT_MOUNT_ROOT",
+    "WSLGIT_RELATIVE_PATHS", "WSLGIT_TRANSLATE_CMDS", "WSLGIT_TRANSLATE_STDERR",
+    "WSLGIT_UNC_OUTPUT", "WSLGIT_VERBOSE", "WSLGIT_WIN_SEP",
+];
+
+/// The environment variable corresponding to the setting `key`.
+fn setting_var(key: &str) -> String {
+    format!("WSLGIT_{}", key.to_uppercase().replace('-', "_"))
+}
+
+/// Parse a basic (`"..."`) or literal (`'...'`) string at the start of
+/// `input`, returning it and the remaining input.
+fn parse_string(input: &str) -> Result<(String, &str), String> {
+    let quote = input.chars().next().ok_or("missing value")?;
+    let mut value = String::new();
+    let mut chars = input[1..].char_indices();
+    while let Some((i, c)) = chars.next() {
+        if c == quote {
+            return Ok((value, &input[i + 2..]));
+        }
+        if c == '\\' && quote == '"' {
+            match chars.next().map(|(_, escaped)| escaped) {
+                Some('\\') => value.push('\\'),
+                Some('"') => value.push('"'),
+                Some('n') => value.push('\n'),
+                Some('t') => value.push('\t'),
+                Some(escaped) => return Err(format!("unsupported escape '\\{}'", escaped)),
+                None => break,
+            }
+        } else {
+            value.push(c);
+        }
+    }
+    Err(String::from("unterminated string"))
+}
+
+/// Parse the value at the start of `input`, returning it as a string and
+/// the remaining input. Arrays of strings are joined with commas.
+fn parse_value(input: &str) -> Result<(String, &str), String> {
+    match input.chars().next() {
+        Some('"') | Some('\'') => parse_string(input),
+        Some('[') => {
+            let mut items = vec![];
+            let mut rest = input[1..].trim_start();
+            while !rest.starts_with(']') {
+                let (item, after_item) = parse_string(rest)?;
+                items.push(item);
+                rest = after_item.trim_start();
+                if let Some(after_comma) = rest.strip_prefix(',') {
+                    rest = after_comma.trim_start();
+                } else if !rest.starts_with(']') {
+                    return Err(String::from("expected ',' or ']' in array"));
+                }
+            }
+            Ok((items.join(","), &rest[1..]))
+        }
+        _ => {
+            let end = input.find(|c: char| c.is_whitespace() || c == '#').unwrap_or(input.len());
+            let (value, rest) = input.split_at(end);
+            let is_bool = value == "true" || value == "false";
+            if !is_bool && value.parse::<i64>().is_err() {
+                return Err(format!("invalid value '{}', strings must be quoted", value));
+            }
+            Ok((value.to_owned(), rest))
+        }
+    }
+}
+
+/// Parse the `key = value` lines of a configuration file. Tables and values
+/// spanning multiple lines are not supported.
+pub fn parse_config(text: &str) -> Result<Config, String> {
+    let mut config = Config::new();
+    for (number, line) in text.lines().enumerate() {
+        let at_line = |err: String| format!("line {}: {}", number + 1, err);
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if line.starts_with('[') {
+            return Err(at_line(String::from("tables are not supported")));
+        }
+        let (key, value) = line.split_once('=')
+            .ok_or_else(|| at_line(String::from("expected 'key = value'")))?;
+        let key = key.trim();
+        if key.is_empty() || !key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-') {
+            return Err(at_line(format!("invalid key '{}'", key)));
+        }
+        let (value, rest) = parse_value(value.trim_start()).map_err(at_line)?;
+        let rest = rest.trim_start();
+        if !rest.is_empty() && !rest.starts_with('#') {
+            return Err(at_line(format!("unexpected '{}' after the value", rest)));
+        }
+        config.insert(setting_var(key), value);
+    }
+    Ok(config)
+}
+
+/// The nearest configuration file in `dir` or one of its parents.
+pub fn find_config_file(dir: &Path) -> Option<PathBuf> {
+    dir.ancestors()
+        .map(|dir| dir.join(CONFIG_FILE_NAME))
+        .find(|path| path.is_file())
+}
+
+/// The per-user configuration file below `appdata`, the `APPDATA` directory:
+/// `%APPDATA%\wslgit\config.toml`.
+pub fn global_config_file(appdata: Option<OsString>) -> Option<PathBuf> {
+    appdata.filter(|appdata| !appdata.is_empty())
+        .map(|appdata| PathBuf::from(appdata).join("wslgit").join("config.toml"))
+}
+
+/// The settings of the configuration file at `path`, which are ignored with
+/// a warning if it cannot be read or parsed.
+fn read_config_file(path: &Path) -> Config {
+    let parsed = fs::read_to_string(path)
+        .map_err(|err| err.to_string())
+        .and_then(|text| parse_config(&text));
+    match parsed {
+        Ok(config) => config,
+        Err(err) => {
+            log!("ignoring {}: {}", path.display(), err);
+            Config::new()
+        }
+    }
+}
+
+/// The settings of the `.wslgit` file `config` which it may change, see
+/// `REPOSITORY_SETTINGS`, and the names of the others.
+pub fn repository_settings(config: Config) -> (Config, Vec<String>) {
+    let (allowed, ignored): (Config, Config) = config.into_iter()
+        .partition(|(name, _)| REPOSITORY_SETTINGS.contains(&name.as_str()));
+    let mut ignored: Vec<String> = ignored.into_keys().collect();
+    ignored.sort();
+    (allowed, ignored)
+}
+
+/// The settings of `global` overridden by the ones of the repository.
+pub fn merge_configs(global: Config, repository: Config) -> Config {
+    let mut config = global;
+    config.extend(repository);
+    config
+}
+
+/// The value of the setting `name`: the environment variable `env_value`,
+/// else the one of `config`. None if the default applies.
+pub fn setting(name: &str, env_value: Option<String>, config: &Config) -> Option<String> {
+    env_value.or_else(|| config.get(name).cloned())
+}
+
+/// The settings of the global configuration file, overridden by the ones of
+/// the configuration file for the working directory.
+pub fn load_config() -> Config {
+    let global = global_config_file(env::var_os("APPDATA"))
+        .filter(|path| path.is_file())
+        .map(|path| read_config_file(&path))
+        .unwrap_or_default();
+    let repository = env::current_dir().ok()
+        .and_then(|cwd| find_config_file(&cwd))
+        .map(|path| {
+            let (config, ignored) = repository_settings(read_config_file(&path));
+            for name in ignored {
+                log!("ignoring {} in {}, it can only be set in the environment or {}",
+                     name, path.display(), "%APPDATA%\\wslgit\\config.toml");
+            }
+            config
+        })
+        .unwrap_or_default();
+    merge_configs(global, repository)
+}
+
+
+#[test]
+fn config_file_parsing() {
+    let config = parse_config("\
+        # settings for this repository\n\
+        distro = \"Ubuntu-22.04\"\n\
+        user = 'dev' # the owner of the keys\n\
+        \n\
+        mount_root = '/windows'\n\
+        translate_cmds = [\"ls-files\", '-remote']\n\
+        unc-output = true\n\
+        editor = \"C:\\\\Program Files\\\\ed.exe \\\"--wait\\\"\"\n").unwrap();
+    assert_eq!(config.len(), 6);
+    assert_eq!(config["WSLGIT_DISTRO"], "Ubuntu-22.04");
+    assert_eq!(config["WSLGIT_USER"], "dev");
+    assert_eq!(config["WSLGIT_MOUNT_ROOT"], "/windows");
+    assert_eq!(config["WSLGIT_TRANSLATE_CMDS"], "ls-files,-remote");
+    assert_eq!(config["WSLGIT_UNC_OUTPUT"], "true");
+    assert_eq!(config["WSLGIT_EDITOR"], "C:\\Program Files\\ed.exe \"--wait\"");
+    assert_eq!(parse_config("translate_cmds = []\n").unwrap()["WSLGIT_TRANSLATE_CMDS"], "");
+
+    assert_eq!(parse_config("distro = Ubuntu").unwrap_err(),
+               "line 1: invalid value 'Ubuntu', strings must be quoted");
+    assert_eq!(parse_config("\n[wsl]\n").unwrap_err(), "line 2: tables are not supported");
+    assert_eq!(parse_config("user = \"dev").unwrap_err(), "line 1: unterminated string");
+    assert_eq!(parse_config("user = 'a' 'b'").unwrap_err(), "line 1: unexpected ''b'' after the value");
+    assert!(parse_config("distro").is_err());
+}
+
+#[test]
+fn config_file_search() {
+    let root = env::temp_dir().join(format!("wslgit-test-config-{}", std::process::id()));
+    let nested = root.join("repo").join("src");
+    fs::create_dir_all(&nested).unwrap();
+    fs::write(root.join(CONFIG_FILE_NAME), "distro = 'Debian'\n").unwrap();
+    assert_eq!(find_config_file(&nested), Some(root.join(CONFIG_FILE_NAME)));
+    fs::write(root.join("repo").join(CONFIG_FILE_NAME), "").unwrap();
+    assert_eq!(find_config_file(&nested), Some(root.join("repo").join(CONFIG_FILE_NAME)));
+    fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn config_precedence() {
+    let global = parse_config("\
+        distro = 'Debian'\n\
+        user = 'me'\n\
+        mount_root = '/windows'\n\
+        forward_env = ['SSL_CERT_FILE']\n").unwrap();
+    let repository = parse_config("\
+        user = 'dev'\n\
+        mount_root = '/'\n").unwrap();
+    let config = merge_configs(global, repository);
+    let env_value = |name: &str| if name == "WSLGIT_MOUNT_ROOT" { Some("/mnt".to_owned()) } else { None };
+    let value = |name: &str| setting(name, env_value(name), &config);
+    // the default, the global and the repository setting, and the environment variable
+    assert_eq!(value("WSLGIT_SHELL_MODE"), None);
+    assert_eq!(value("WSLGIT_DISTRO"), Some("Debian".to_owned()));
+    assert_eq!(value("WSLGIT_FORWARD_ENV"), Some("SSL_CERT_FILE".to_owned()));
+    assert_eq!(value("WSLGIT_USER"), Some("dev".to_owned()));
+    assert_eq!(value("WSLGIT_MOUNT_ROOT"), Some("/mnt".to_owned()));
+
+    // a repository must not choose the programs run, nor the distro and user
+    let (repository, ignored) = repository_settings(parse_config("\
+        drive_case = 'upper'\n\
+        distro = 'Debian'\n\
+        user = 'root'\n\
+        path_map = 'C:\\repo=/srv/evil'\n\
+        stderr_filter = '^fatal'\n\
+        wsl_exe = '/tmp/payload'\n\
+        cmd = 'sh -c payload'\n\
+        git_bin = '/tmp/payload'\n\
+        wsl_args = '--exec /tmp/payload'\n").unwrap());
+    assert_eq!(repository.into_iter().collect::<Vec<_>>(),
+               vec![("WSLGIT_DRIVE_CASE".to_owned(), "upper".to_owned())]);
+    assert_eq!(ignored, vec!["WSLGIT_CMD", "WSLGIT_DISTRO", "WSLGIT_GIT_BIN", "WSLGIT_PATH_MAP",
+                             "WSLGIT_STDERR_FILTER", "WSLGIT_USER", "WSLGIT_WSL_ARGS", "WSLGIT_WSL_EXE"]);
+
+    assert_eq!(global_config_file(Some(OsString::from("/appdata"))),
+               Some(Path::new("/appdata").join("wslgit").join("config.toml")));
+    assert_eq!(global_config_file(Some(OsString::new())), None);
+    assert_eq!(global_config_file(None), None);
+}
@@ -0,0 +1,86 @@
+//! `wslgit install-credential-helper`, which makes git inside WSL use the
+//! Git Credential Manager of Windows, sharing the credentials with Windows.
+
+use std::env;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+
+use wslgit_for_jetbrains::{shell_escape, Error, Translator};
+
+use super::{config_var, format_command, forwarded_exit_code, git_bin, wsl_command, TRANSLATOR};
+
+/// Locations of the Git Credential Manager: bundled with Git for Windows,
+/// installed on its own for all users, or for the current user.
+fn credential_manager_candidates(program_files: Option<OsString>,
+                                 program_files_x86: Option<OsString>,
+                                 local_app_data: Option<OsString>) -> Vec<PathBuf> {
+    let mut candidates = vec![];
+    for dir in program_files.iter().chain(program_files_x86.iter()).map(PathBuf::from) {
+        candidates.push(dir.join("Git").join("mingw64").join("bin").join("git-credential-manager.exe"));
+        candidates.push(dir.join("Git").join("mingw64").join("libexec").join("git-core")
+            .join("git-credential-manager-core.exe"));
+        candidates.push(dir.join("Git Credential Manager").join("git-credential-manager.exe"));
+    }
+    for dir in local_app_data.iter().map(PathBuf::from) {
+        candidates.push(dir.join("Programs").join("Git Credential Manager")
+            .join("git-credential-manager.exe"));
+    }
+    candidates
+}
+
+/// The value of `credential.helper` running the `credential_manager` from
+/// inside WSL. Git runs it through the shell, so the path is quoted.
+fn credential_helper(credential_manager: &Path, translator: &Translator) -> Result<String, Error> {
+    let unix_path = translator.to_unix(credential_manager.to_string_lossy().into_owned())?;
+    Ok(shell_escape(unix_path))
+}
+
+/// Configure `credential_helper` in the global git configuration inside WSL,
+/// or with `dry_run` only print the command doing so.
+pub fn install(dry_run: bool) -> Result<i32, Error> {
+    let credential_manager = credential_manager_candidates(
+            env::var_os("ProgramFiles"), env::var_os("ProgramFiles(x86)"),
+            env::var_os("LOCALAPPDATA"))
+        .into_iter()
+        .find(|candidate| candidate.is_file())
+        .ok_or(Error::NoCredentialManager)?;
+    let helper = credential_helper(&credential_manager, &TRANSLATOR)?;
+    let mut command = wsl_command();
+    command.args([git_bin(config_var("WSLGIT_GIT_BIN")), String::from("config"),
+                  String::from("--global"), String::from("credential.helper"), shell_escape(helper.clone())]);
+    println!("credential.helper = {}", helper);
+    if dry_run {
+        println!("{}", format_command(&command));
+        return Ok(0);
+    }
+    let status = command
+        .stdin(Stdio::null())
+        .status()
+        .map_err(|err| Error::Spawn(command.get_program().to_string_lossy().into_owned(), err))?;
+    Ok(forwarded_exit_code(status))
+}
+
+
+#[test]
+fn credential_manager_setup() {
+    let candidates = credential_manager_candidates(
+        Some("C:\\Program Files".into()), None, Some("C:\\Users\\me\\AppData\\Local".into()));
+    assert_eq!(candidates.len(), 4);
+    assert_eq!(candidates[0], Path::new("C:\\Program Files").join("Git").join("mingw64").join("bin")
+        .join("git-credential-manager.exe"));
+    assert_eq!(candidates[3], Path::new("C:\\Users\\me\\AppData\\Local").join("Programs")
+        .join("Git Credential Manager").join("git-credential-manager.exe"));
+    assert!(credential_manager_candidates(None, None, None).is_empty());
+
+    let translator = Translator::default();
+    assert_eq!(
+        credential_helper(Path::new("C:\\Program Files\\Git\\mingw64\\bin\\git-credential-manager.exe"),
+                          &translator).unwrap(),
+        "'/mnt/c/Program Files/Git/mingw64/bin/git-credential-manager.exe'");
+    assert_eq!(
+        credential_helper(Path::new("C:\\Program Files (x86)\\Git Credential Manager\\git-credential-manager.exe"),
+                          &translator).unwrap(),
+        "'/mnt/c/Program Files (x86)/Git Credential Manager/git-credential-manager.exe'");
+    assert_eq!(credential_helper(Path::new("C:\\GCM\\gcm.exe"), &translator).unwrap(), "/mnt/c/GCM/gcm.exe");
+}
@@ -0,0 +1,108 @@
+//! The `wslgit doctor` report of the detected WSL setup, for bug reports.
+
+use std::env;
+
+use wslgit_for_jetbrains::{Error, Translator};
+
+use super::{config, find_distro, registered_distros, DistroInfo, WslVersion, TRANSLATOR, WSL_DISTRO,
+            WSL_EXE, WSL_USER};
+
+/// Windows paths whose translation is checked by the report.
+const SAMPLE_PATHS: &[&str] = &["C:\\Users", "D:\\repos\\a b\\file.txt"];
+
+/// Translate the Windows `path` for WSL and back, as done for arguments
+/// and output. Returns the Linux path and the Windows path it gives back.
+pub fn round_trip(translator: &Translator, path: &str) -> Result<(String, String), Error> {
+    let unix_path = translator.to_unix(path.to_owned())?;
+    let win_path = translator.to_win_path(unix_path.clone());
+    Ok((unix_path, win_path))
+}
+
+/// Whether the Windows paths `a` and `b` are the same, ignoring the case of
+/// the drive letter and the kind of separators.
+pub fn is_same_win_path(a: &str, b: &str) -> bool {
+    let normalize = |path: &str| -> String {
+        let path = path.replace('\\', "/");
+        let mut chars = path.chars();
+        let drive = chars.next().map(|c| c.to_ascii_lowercase().to_string()).unwrap_or_default();
+        format!("{}{}", drive, chars.as_str()).trim_end_matches('/').to_owned()
+    };
+    normalize(a) == normalize(b)
+}
+
+fn describe_distro(distro: &DistroInfo) -> String {
+    match distro.version {
+        Some(WslVersion::Wsl1) => format!("{} (WSL 1)", distro.name),
+        Some(WslVersion::Wsl2) => format!("{} (WSL 2)", distro.name),
+        None => distro.name.clone(),
+    }
+}
+
+/// Print the report to stdout.
+pub fn print_report() {
+    match *WSL_EXE {
+        Some(ref wsl) => println!("wsl: {}", wsl.display()),
+        None => println!("wsl: not found"),
+    }
+
+    let distros = registered_distros();
+    match find_distro(&distros, None) {
+        Some(distro) => println!("default distro: {}", describe_distro(distro)),
+        None => println!("default distro: unknown"),
+    }
+    match *WSL_DISTRO {
+        Some(ref selected) => match find_distro(&distros, Some(selected)) {
+            Some(distro) => println!("selected distro: {}", describe_distro(distro)),
+            None => println!("selected distro: {} (not registered)", selected),
+        },
+        None => println!("selected distro: (default)"),
+    }
+    println!("user: {}", WSL_USER.as_deref().unwrap_or("(default)"));
+
+    let global_config_file = config::global_config_file(env::var_os("APPDATA"))
+        .filter(|path| path.is_file());
+    match global_config_file {
+        Some(path) => println!("global configuration file: {}", path.display()),
+        None => println!("global configuration file: none"),
+    }
+    let config_file = env::current_dir().ok().and_then(|cwd| config::find_config_file(&cwd));
+    match config_file {
+        Some(path) => println!("configuration file: {}", path.display()),
+        None => println!("configuration file: none"),
+    }
+
+    println!("mount root: {}", TRANSLATOR.mount_root());
+    let mut drive_mounts: Vec<_> = TRANSLATOR.drive_mounts().iter().collect();
+    drive_mounts.sort();
+    if drive_mounts.is_empty() {
+        println!("drive mounts: none discovered");
+    }
+    for (drive, mount_point) in drive_mounts {
+        println!("drive mount: {}: -> {}", drive, mount_point);
+    }
+
+    let cwd = env::current_dir().ok().and_then(|cwd| cwd.to_str().map(String::from));
+    for path in SAMPLE_PATHS.iter().map(|path| path.to_string()).chain(cwd) {
+        match round_trip(&TRANSLATOR, &path) {
+            Ok((unix_path, win_path)) => {
+                let result = if is_same_win_path(&path, &win_path) { "ok" } else { "MISMATCH" };
+                println!("round trip: {} -> {} -> {}: {}", path, unix_path, win_path, result);
+            }
+            Err(err) => println!("round trip: {}: {}", path, err),
+        }
+    }
+}
+
+
+#[test]
+fn doctor_round_trips() {
+    let translator = Translator::default();
+    for path in SAMPLE_PATHS {
+        let (_, win_path) = round_trip(&translator, path).unwrap();
+        assert!(is_same_win_path(path, &win_path), "{} -> {}", path, win_path);
+    }
+    assert_eq!(round_trip(&translator, "C:\\Users").unwrap(),
+               ("/mnt/c/Users".to_owned(), "c:/Users".to_owned()));
+    assert!(is_same_win_path("C:\\Users\\", "c:/Users"));
+    assert!(!is_same_win_path("C:\\a", "C:\\b"));
+}
@@ -0,0 +1,2449 @@
+//! Translation of paths and command lines between Windows and WSL, as done by
+//! the `wslgit` binary.
+
+#[macro_use]
+extern crate lazy_static;
+extern crate regex;
+
+use std::collections::HashMap;
+use std::env;
+use std::fmt;
+use std::io;
+use std::path::Path;
+
+use regex::{Captures, Regex};
+
+/// Failures of wslgit itself, reported instead of running git.
+#[derive(Debug)]
+pub enum Error {
+    /// An argument looks like a path, but cannot be translated for WSL.
+    UnsupportedPath(String),
+    /// A path is on a drive which is not mounted inside WSL.
+    UnmountedDrive(String),
+    /// The distro selected by `WSLGIT_DISTRO` does not exist.
+    UnregisteredDistro(String),
+    /// No distro is installed, so there is no default one to run git in.
+    NoDistro,
+    /// The Git Credential Manager of Windows was not found.
+    NoCredentialManager,
+    /// The program could not be started.
+    Spawn(String, io::Error),
+    /// Waiting for the started program failed.
+    Wait(io::Error),
+    /// The arguments could not be written to a temporary file.
+    TempFile(io::Error),
+}
+
+impl Error {
+    /// The exit code of wslgit for this error, following the shell
+    /// conventions for commands which cannot be found or executed.
+    pub fn exit_code(&self) -> i32 {
+        match *self {
+            Error::Spawn(_, ref err) if err.kind() == io::ErrorKind::NotFound => 127,
+            Error::Spawn(..) => 126,
+            Error::UnsupportedPath(_) | Error::UnmountedDrive(_) => 2,
+            Error::NoDistro => 3,
+            _ => 1,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::UnsupportedPath(ref path) =>
+                write!(f, "cannot translate the path '{}' for WSL", path),
+            Error::UnmountedDrive(ref drive) =>
+                write!(f, "the drive {}: is not mounted inside WSL, see 'mount -t drvfs'", drive),
+            Error::UnregisteredDistro(ref distro) =>
+                write!(f, "the WSL distribution '{}' from WSLGIT_DISTRO is not registered, \
+                           see 'wsl --list'", distro),
+            Error::NoDistro =>
+                write!(f, "no WSL distribution is installed, install one with 'wsl --install' \
+                           or set the default one with 'wsl --set-default <distro>'"),
+            Error::NoCredentialManager =>
+                write!(f, "cannot find the Git Credential Manager, install Git for Windows \
+                           or the Git Credential Manager"),
+            Error::Spawn(ref program, ref err) if err.kind() == io::ErrorKind::NotFound =>
+                write!(f, "cannot find {}, make sure WSL is installed", program),
+            Error::Spawn(ref program, ref err) => write!(f, "failed to execute {}: {}", program, err),
+            Error::Wait(ref err) => write!(f, "failed to wait for git: {}", err),
+            Error::TempFile(ref err) =>
+                write!(f, "failed to write the arguments to a temporary file: {}", err),
+        }
+    }
+}
+
+/// Prefix of a Windows path, parsed without relying on the host platform.
+#[derive(Debug, PartialEq)]
+enum WinPrefix<'a> {
+    /// `C:` or `\\?\C:`
+    Disk(char),
+    /// `\\wsl$\<distro>` or `\\wsl.localhost\<distro>`
+    Wsl(&'a str),
+    /// Any other `\\server\share`
+    Unc(&'a str, &'a str),
+}
+
+fn is_separator(c: char) -> bool {
+    c == '\\' || c == '/'
+}
+
+fn split_unc(unc: &str) -> (Option<WinPrefix<'_>>, &str) {
+    let mut parts = unc.splitn(3, is_separator);
+    let server = parts.next().unwrap_or_default();
+    let share = parts.next().unwrap_or_default();
+    let rest = &unc[(server.len() + share.len() + 1).min(unc.len())..];
+    let is_wsl_host = server.eq_ignore_ascii_case("wsl$")
+        || server.eq_ignore_ascii_case("wsl.localhost");
+    if is_wsl_host {
+        (Some(WinPrefix::Wsl(share)), rest)
+    } else {
+        (Some(WinPrefix::Unc(server, share)), rest)
+    }
+}
+
+/// Whether `server` and `share` look like the ones of a network path, so
+/// arguments like the regex `\\\\bfoo` are not taken for one.
+fn is_unc_share(server: &str, share: &str) -> bool {
+    !server.is_empty()
+        && server.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '.' || c == '_')
+        && !share.is_empty()
+        && !share.contains(|c: char| c.is_control() || "*?|<>\"".contains(c))
+}
+
+/// Split a Windows path into its prefix (if any) and the remaining path.
+fn split_win_prefix(path: &str) -> (Option<WinPrefix<'_>>, &str) {
+    if let Some(verbatim) = path.strip_prefix("\\\\?\\") {
+        if verbatim.len() >= 4 && verbatim[..4].eq_ignore_ascii_case("UNC\\") {
+            return split_unc(&verbatim[4..]);
+        }
+        return match split_win_prefix(verbatim) {
+            (Some(WinPrefix::Disk(d)), rest) => (Some(WinPrefix::Disk(d)), rest),
+            _ => (None, path),
+        };
+    }
+    let mut chars = path.chars();
+    match (chars.next(), chars.next()) {
+        (Some(a), Some(b)) if is_separator(a) && is_separator(b) => split_unc(&path[2..]),
+        (Some(d), Some(':')) if d.is_ascii_alphabetic() => (Some(WinPrefix::Disk(d)), &path[2..]),
+        _ => (None, path),
+    }
+}
+
+/// Mount root used by WSL when `/etc/wsl.conf` does not configure one.
+pub const DEFAULT_MOUNT_ROOT: &str = "/mnt";
+
+/// The Linux directory `drive` is mounted at, from the discovered
+/// `drive_mounts` or else below `mount_root`.
+pub fn get_prefix_for_drive(drive: &str, mount_root: &str,
+                            drive_mounts: &HashMap<String, String>) -> String {
+    match drive_mounts.get(drive) {
+        Some(mount_point) => mount_point.clone(),
+        None => format!("{}/{}", mount_root, drive),
+    }
+}
+
+/// A path rooted with a forward slash cannot be a Windows absolute path,
+/// it already points inside the Linux filesystem of the distro.
+fn is_native_unix_path(path: &str) -> bool {
+    path.starts_with('/') && !path.starts_with("//")
+}
+
+/// Schemes of git URLs, which are passed to git unchanged.
+const URL_SCHEMES: &[&str] = &["http", "https", "ssh", "git", "file", "ftp", "ftps", "git+ssh", "ssh+git"];
+
+/// Whether `arg` is a git URL rather than a path, either with a scheme like
+/// `https://host/repo` or scp-like as in `user@host:repo`.
+fn is_url(arg: &str) -> bool {
+    if let Some((scheme, _)) = arg.split_once("://") {
+        return URL_SCHEMES.iter().any(|url_scheme| url_scheme.eq_ignore_ascii_case(scheme));
+    }
+    match arg.split_once(':') {
+        Some((user_host, _)) => user_host.contains('@') && !user_host.contains(is_separator),
+        None => false,
+    }
+}
+
+/// The Windows path of a `file://` URL like `file:///C:/repo`.
+fn file_url_win_path(url: &str) -> Option<&str> {
+    let path = match url.get(..7) {
+        Some(scheme) if scheme.eq_ignore_ascii_case("file://") => &url[7..],
+        _ => return None,
+    };
+    let path = path.strip_prefix('/').unwrap_or(path);
+    match split_win_prefix(path) {
+        (Some(WinPrefix::Disk(_)), rest) if rest.starts_with(is_separator) => Some(path),
+        _ => None,
+    }
+}
+
+/// The current directory cmd keeps for `drive`, used to resolve
+/// drive-relative paths like `C:foo`.
+fn current_dir_on_drive(drive: char) -> Option<String> {
+    let cwd = env::current_dir().ok()
+        .and_then(|cwd| cwd.to_str().map(String::from));
+    if let Some(cwd) = cwd {
+        if let (Some(WinPrefix::Disk(d)), _) = split_win_prefix(&cwd) {
+            if d.eq_ignore_ascii_case(&drive) {
+                return Some(cwd);
+            }
+        }
+    }
+    env::var(format!("={}:", drive.to_ascii_uppercase())).ok()
+}
+
+/// Make the drive-relative path `<drive>:<rest>` absolute, relative to the
+/// current directory of the drive or to its root if it has none.
+fn drive_relative_path(drive: char, rest: &str, drive_cwd: Option<&str>) -> String {
+    match drive_cwd {
+        Some(cwd) => format!("{}\\{}", cwd.trim_end_matches(is_separator), rest),
+        None => format!("{}:\\{}", drive, rest),
+    }
+}
+
+/// Which relative arguments are translated as paths.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RelativePaths {
+    /// Only explicitly relative ones like `.\foo` or `..\foo` (the default).
+    Explicit,
+    /// Also the ones naming an existing file or directory, like `src\foo`.
+    Existing,
+}
+
+/// Whether `arg` starts with `.` or `..` and a separator.
+fn is_explicit_relative(arg: &str) -> bool {
+    let rest = arg.strip_prefix("..").or_else(|| arg.strip_prefix('.'));
+    rest.is_some_and(|rest| rest.starts_with(is_separator))
+}
+
+/// The Linux directory a drive is mounted at, or the error if it is not.
+/// The drive letter is passed in the case it is written in.
+type DrivePrefix<'a> = dyn Fn(&str) -> Result<String, Error> + 'a;
+
+/// Whether `arg` may be changed by the translation: Windows paths and URLs
+/// contain `:` or `\\`, and `./` paths are normalized. Anything else, like
+/// flags, refs or commit ids, translates to itself, and is skipped without
+/// looking at the filesystem.
+fn may_need_translation(arg: &str) -> bool {
+    arg.contains(':') || arg.contains('\\') || arg.starts_with("./")
+}
+
+/// Translate `argument` to a Linux path if it is an absolute Windows path or
+/// a relative one selected by `relative_paths`, or any relative argument if
+/// `is_path`, also as the value of a `--option=` or enclosed in quotes.
+/// Absolute paths embedded in the value of a `--option=`, like in
+/// `--pretty=format:%H C:\path`, are translated as well.
+fn translate_to_unix(argument: String, drive_prefix: &DrivePrefix, is_path: bool,
+                     relative_paths: RelativePaths, bind_mounts: &[BindMount])
+                     -> Result<String, Error> {
+    if !is_path && !may_need_translation(&argument) {
+        return Ok(argument);
+    }
+    // the magic of a pathspec like `:(exclude)C:\path` is kept as it is
+    if let Some((magic, path)) = split_absolute_pathspec(&argument) {
+        let path = translate_to_unix(path.to_owned(), drive_prefix, true, relative_paths, bind_mounts)?;
+        return Ok(join_pathspec_magic(magic, &path));
+    }
+    {
+        let (argname, arg) = if argument.starts_with("--")
+            && argument.contains('=') {
+            let parts: Vec<&str> = argument
+                .splitn(2, '=')
+                .collect();
+            (format!("{}=", parts[0]), parts[1])
+        } else {
+            ("".to_owned(), argument.as_ref())
+        };
+        if let Some((quote, inner)) = unquote(arg) {
+            let inner = translate_to_unix(
+                inner.to_owned(), drive_prefix, is_path, relative_paths, bind_mounts)?;
+            return Ok(format!("{}{}{}{}", argname, quote, inner, quote));
+        }
+        if let Some(path) = file_url_win_path(arg) {
+            let wsl_path = translate_to_unix(
+                path.to_owned(), drive_prefix, true, relative_paths, bind_mounts)?;
+            return Ok(format!("{}file://{}", argname, wsl_path));
+        }
+        if is_native_unix_path(arg) || is_url(arg) {
+            return Ok(argument);
+        }
+        // `/` and `\` are both accepted as separators, even mixed
+        let (prefix, rest) = split_win_prefix(arg);
+        if let Some(WinPrefix::Disk(d)) = prefix {
+            if !rest.starts_with(is_separator) {
+                // `a:b` may as well be a refspec or a pattern, unless it is a path
+                if !is_path {
+                    return Ok(argument);
+                }
+                let resolved = drive_relative_path(d, rest, current_dir_on_drive(d).as_deref());
+                return translate_to_unix(
+                    format!("{}{}", argname, resolved), drive_prefix, is_path, relative_paths,
+                    bind_mounts);
+            }
+        }
+        let is_absolute = match prefix {
+            Some(WinPrefix::Disk(_)) => rest.starts_with(is_separator),
+            Some(_) => true,
+            None => false,
+        };
+        // backslashes are separators on Windows, normalize for other hosts
+        let is_existing = || relative_paths == RelativePaths::Existing
+            && Path::new(&arg.replace('\\', "/")).exists();
+        if is_absolute || is_path || is_explicit_relative(arg) || is_existing() {
+            let mut wsl_path = match prefix {
+                Some(WinPrefix::Disk(d)) => drive_prefix(&d.to_string())?,
+                // already inside the Linux filesystem of the distro
+                Some(WinPrefix::Wsl(_)) => String::from("/"),
+                Some(WinPrefix::Unc(server, share)) if is_path || is_unc_share(server, share) =>
+                    return Err(Error::UnsupportedPath(arg.to_owned())),
+                Some(WinPrefix::Unc(..)) => return Ok(argument),
+                None => String::new(),
+            };
+            let keeps_cur_dir = prefix.is_none() && !rest.starts_with(is_separator);
+            // like Windows, `..` in absolute paths is resolved and stops at the root
+            let root_len = wsl_path.len();
+            for (i, component) in rest.split(is_separator)
+                .filter(|component| !component.is_empty())
+                .enumerate() {
+                if component == "." && (i > 0 || !keeps_cur_dir) {
+                    continue;
+                }
+                if component == ".." && prefix.is_some() {
+                    let parent_len = wsl_path.rfind('/').unwrap_or_default();
+                    wsl_path.truncate(parent_len.max(root_len));
+                    continue;
+                }
+                if !wsl_path.is_empty() && !wsl_path.ends_with('/') {
+                    wsl_path.push('/');
+                }
+                wsl_path.push_str(component);
+            }
+            // the root of a drive is a directory, `/mnt/c` could be read as a file
+            if let Some(WinPrefix::Disk(_)) = prefix {
+                if wsl_path.len() == root_len && !wsl_path.ends_with('/') {
+                    wsl_path.push('/');
+                }
+                wsl_path = apply_bind_mounts(wsl_path, bind_mounts);
+            }
+            return Ok(format!("{}{}", &argname, &wsl_path));
+        }
+        // like `--pretty=format:%H C:\path`
+        if !argname.is_empty() && has_embedded_win_paths(arg) {
+            let value = translate_embedded_to_unix(arg, drive_prefix, bind_mounts);
+            return Ok(format!("{}{}", argname, value));
+        }
+    }
+    Ok(argument)
+}
+
+/// The quote and the inner text of `arg` if it is enclosed in double or
+/// single quotes, like `"C:\Program Files\Git"` passed on with its quotes.
+fn unquote(arg: &str) -> Option<(char, &str)> {
+    let quote = arg.chars().next().filter(|&c| c == '"' || c == '\'')?;
+    arg[1..].strip_suffix(quote).map(|inner| (quote, inner))
+}
+
+lazy_static! {
+    /// Absolute Windows paths anywhere in an argument, up to a space, quote
+    /// or list separator.
+    static ref EMBEDDED_WIN_PATH_RE: Regex = Regex::new(
+        r#"(?P<pre>^|[^\w])(?P<path>[A-Za-z]:[\\/][^\s"'`;,|<>]*)"#).unwrap();
+}
+
+/// Whether `arg` contains an absolute Windows path after its start, or after
+/// the start of its `--option=` value, like `C:\a;C:\b` or `ssh -i C:\key`.
+fn has_embedded_win_paths(arg: &str) -> bool {
+    let value_start = if arg.starts_with("--") { arg.find('=').map_or(0, |pos| pos + 1) } else { 0 };
+    EMBEDDED_WIN_PATH_RE.captures_iter(arg)
+        .any(|caps| caps.name("path").is_some_and(|path| path.start() != value_start))
+}
+
+/// Translate each absolute Windows path in `arg` to a Linux path in place,
+/// keeping the ones which cannot be translated.
+fn translate_embedded_to_unix(arg: &str, drive_prefix: &DrivePrefix,
+                              bind_mounts: &[BindMount]) -> String {
+    String::from(EMBEDDED_WIN_PATH_RE.replace_all(arg, |caps: &Captures| {
+        let path = translate_to_unix(
+            caps["path"].to_owned(), drive_prefix, true, RelativePaths::Explicit, bind_mounts)
+            .unwrap_or_else(|_| caps["path"].to_owned());
+        format!("{}{}", &caps["pre"], path)
+    }))
+}
+
+/// Expand the `%NAME%` references in `arg` to the `vars` by upper case name,
+/// leaving the references to unknown variables as they are.
+fn expand_win_env_vars(arg: &str, vars: &HashMap<String, String>) -> String {
+    let mut expanded = String::with_capacity(arg.len());
+    let mut rest = arg;
+    while let Some(start) = rest.find('%') {
+        expanded.push_str(&rest[..start]);
+        let after_start = &rest[start + 1..];
+        match after_start.find('%') {
+            Some(len) => {
+                let name = &after_start[..len];
+                match vars.get(&name.to_uppercase()).filter(|_| !name.is_empty()) {
+                    Some(value) => expanded.push_str(value),
+                    None => expanded.push_str(&rest[start..start + len + 2]),
+                }
+                rest = &after_start[len + 1..];
+            }
+            None => {
+                expanded.push_str(&rest[start..]);
+                rest = "";
+            }
+        }
+    }
+    expanded.push_str(rest);
+    expanded
+}
+
+/// A directory below a drive mount which is bind mounted elsewhere, e.g.
+/// `/mnt/c/repos` to `/home/me/repos`.
+#[derive(Clone, Debug, PartialEq)]
+struct BindMount {
+    mount_path: String,
+    bind_path: String,
+}
+
+/// The length of the `prefix` of `path`, comparing ASCII letters case
+/// insensitively like Windows does, if `path` is `prefix` or below it.
+fn path_prefix_len(path: &str, prefix: &str) -> Option<usize> {
+    let head = path.get(..prefix.len())?;
+    let rest = &path[prefix.len()..];
+    if head.eq_ignore_ascii_case(prefix) && (rest.is_empty() || rest.starts_with('/')) {
+        Some(prefix.len())
+    } else {
+        None
+    }
+}
+
+/// Move `wsl_path` below the `bind_mounts` it falls into.
+fn apply_bind_mounts(wsl_path: String, bind_mounts: &[BindMount]) -> String {
+    for bind_mount in bind_mounts {
+        if let Some(len) = path_prefix_len(&wsl_path, &bind_mount.mount_path) {
+            return format!("{}{}", bind_mount.bind_path, &wsl_path[len..]);
+        }
+    }
+    wsl_path
+}
+
+/// Build the regex matching paths below the drive mounts in `mount_root`
+/// or below any of the discovered `drive_mounts`. With `native_paths`, any
+/// other absolute Linux path starting a word is matched as well.
+pub fn wslpath_regex(mount_root: &str, drive_mounts: &HashMap<String, String>,
+                     native_paths: bool) -> Regex {
+    build_wslpath_regex(mount_root, drive_mounts, native_paths, &[])
+}
+
+/// Like `wslpath_regex`, also matching the `bind_paths` and what follows
+/// them up to the next whitespace.
+fn build_wslpath_regex(mount_root: &str, drive_mounts: &HashMap<String, String>,
+                       native_paths: bool, bind_paths: &[&str]) -> Regex {
+    let mut bind_paths = bind_paths.to_vec();
+    bind_paths.sort_by_key(|bind_path| std::cmp::Reverse(bind_path.len()));
+    let mut pattern = String::from("(?m)");
+    if !bind_paths.is_empty() {
+        pattern.push_str(&format!(
+            r"(?P<bind_pre>^|(?i:file)://|[^\w.-]|\x1b\[[0-9;]*m)(?P<bind>{})(?P<bind_rest>[^\s\x00-\x1f]*)|",
+            bind_paths.iter()
+                .map(|bind_path| regex::escape(bind_path))
+                .collect::<Vec<String>>()
+                .join("|")));
+    }
+    let mut mount_points: Vec<&String> = drive_mounts.values()
+        .filter(|mount_point| *mount_point != "/")
+        .collect();
+    // prefer the most specific mount point
+    mount_points.sort_by_key(|mount_point| std::cmp::Reverse(mount_point.len()));
+    let mut mount_alternatives: Vec<String> = vec![];
+    if !mount_points.is_empty() {
+        mount_alternatives.push(format!("(?P<mount>{})", mount_points.iter()
+            .map(|mount_point| regex::escape(mount_point))
+            .collect::<Vec<String>>()
+            .join("|")));
+    }
+    mount_alternatives.push(format!("{}/(?P<drive>[A-Za-z])", regex::escape(mount_root)));
+    pattern.push_str(&format!(
+        r"(?P<pre>^|(?i:file)://|[^\w.-]|\x1b\[[0-9;]*m)(?:{})(?P<path>/[^\s\x00-\x1f]*)",
+        mount_alternatives.join("|")));
+    if native_paths {
+        pattern.push_str(
+            r"|(?P<native_pre>^|[\s\x00]|\x1b\[[0-9;]*m)(?P<native>/[^\s\x00-\x1f]*)");
+    }
+    Regex::new(&pattern).expect("Failed to compile WSLPATH regex")
+}
+
+/// Substrings of which any match of the regex of `build_wslpath_regex`
+/// contains at least one, to skip the regex for most lines of the output.
+fn wslpath_needles(mount_root: &str, drive_mounts: &HashMap<String, String>,
+                   native_paths: bool, bind_paths: &[&str]) -> Vec<String> {
+    if native_paths {
+        return vec![String::from("/")];
+    }
+    let mut needles = vec![format!("{}/", mount_root)];
+    needles.extend(drive_mounts.values()
+        .filter(|mount_point| *mount_point != "/")
+        .cloned());
+    needles.extend(bind_paths.iter().map(|bind_path| bind_path.to_string()));
+    needles
+}
+
+/// The separator of the Windows paths translated from Linux paths.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum WinSeparator {
+    /// `c:/Users/me`, understood by most Windows programs (the default).
+    Slash,
+    /// `c:\Users\me`, for programs requiring native Windows paths.
+    Backslash,
+}
+
+/// The case of the drive letter in the Linux directories of the drives
+/// mounted below the mount root.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DriveCase {
+    /// `/mnt/c`, as WSL mounts the drives (the default).
+    Lower,
+    /// `/mnt/C`, for drives mounted by hand with upper case letters.
+    Upper,
+    /// The case the drive letter is written in, `C:` to `/mnt/C`.
+    Preserve,
+}
+
+impl DriveCase {
+    fn apply(self, drive: &str) -> String {
+        match self {
+            DriveCase::Lower => drive.to_ascii_lowercase(),
+            DriveCase::Upper => drive.to_ascii_uppercase(),
+            DriveCase::Preserve => drive.to_owned(),
+        }
+    }
+}
+
+/// Split the punctuation following a path in text off the matched `path`,
+/// like the `)` in `(/mnt/c/a)` or the `,` in `/mnt/c/a, /mnt/c/b`.
+/// Closing brackets which have an opening one in the path are kept.
+fn split_trailing_punctuation(path: &str) -> (&str, &str) {
+    let mut end = path.len();
+    while end > 0 {
+        let head = &path[..end];
+        let is_punctuation = match head.as_bytes()[end - 1] {
+            b',' | b';' => true,
+            b')' => head.matches('(').count() < head.matches(')').count(),
+            b']' => head.matches('[').count() < head.matches(']').count(),
+            _ => false,
+        };
+        if !is_punctuation {
+            break;
+        }
+        end -= 1;
+    }
+    path.split_at(end)
+}
+
+fn win_path_replacement(caps: &Captures, drive_mounts: &HashMap<String, String>,
+                        unc_distro: Option<&str>, separator: WinSeparator) -> String {
+    if let (Some(native), Some(distro)) = (caps.name("native"), unc_distro) {
+        let (native, trailing) = split_trailing_punctuation(native.as_str());
+        return format!("{}\\\\wsl$\\{}{}{}",
+                       &caps["native_pre"], distro, native.replace('/', "\\"), trailing);
+    }
+    let drive = match caps.name("mount") {
+        Some(mount_point) => drive_mounts.iter()
+            .find(|&(_, mp)| mp == mount_point.as_str())
+            .map(|(drive, _)| drive.as_str())
+            .unwrap_or_default(),
+        None => &caps["drive"],
+    };
+    // `file:///c:/...` for URLs, which always use slashes
+    let is_url = caps["pre"].ends_with("://");
+    let url_slash = if is_url { "/" } else { "" };
+    let (path, trailing) = split_trailing_punctuation(&caps["path"]);
+    let path = if separator == WinSeparator::Backslash && !is_url {
+        path.replace('/', "\\")
+    } else {
+        path.to_owned()
+    };
+    format!("{}{}{}:{}{}", &caps["pre"], url_slash, drive, path, trailing)
+}
+
+/// Translate the values of the JSON string literals in the output `line`
+/// with `translate`. Literals which change are escaped again, the others and
+/// all text outside of literals are kept as they are.
+fn translate_json_strings(line: &str, translate: &dyn Fn(String) -> String) -> String {
+    let mut translated = String::with_capacity(line.len());
+    let mut rest = line;
+    while let Some(start) = rest.find('"') {
+        translated.push_str(&rest[..=start]);
+        rest = &rest[start + 1..];
+        let end = match json_string_len(rest) {
+            Some(end) => end,
+            // unterminated, e.g. split across lines
+            None => break,
+        };
+        let literal = &rest[..end];
+        match unescape_json(literal) {
+            Some(value) => {
+                let value_translated = translate(value.clone());
+                if value_translated == value {
+                    translated.push_str(literal);
+                } else {
+                    translated.push_str(&escape_json(&value_translated));
+                }
+            }
+            None => translated.push_str(literal),
+        }
+        translated.push('"');
+        rest = &rest[end + 1..];
+    }
+    translated.push_str(rest);
+    translated
+}
+
+/// The length of the JSON string literal starting `text`, up to its closing
+/// quote, or `None` if it is not closed.
+fn json_string_len(text: &str) -> Option<usize> {
+    let mut is_escaped = false;
+    for (i, b) in text.bytes().enumerate() {
+        match b {
+            _ if is_escaped => is_escaped = false,
+            b'\\' => is_escaped = true,
+            b'"' => return Some(i),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// The value of a JSON string `literal`, or `None` for invalid escapes.
+fn unescape_json(literal: &str) -> Option<String> {
+    let mut value = String::with_capacity(literal.len());
+    let mut chars = literal.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            value.push(c);
+            continue;
+        }
+        value.push(match chars.next()? {
+            c @ ('"' | '\\' | '/') => c,
+            'b' => '\u{8}',
+            'f' => '\u{c}',
+            'n' => '\n',
+            'r' => '\r',
+            't' => '\t',
+            'u' => {
+                let unit = json_utf16_unit(&mut chars)?;
+                if (0xd800..0xdc00).contains(&unit) {
+                    // a surrogate pair like `\ud83d\ude00`
+                    if chars.next()? != '\\' || chars.next()? != 'u' {
+                        return None;
+                    }
+                    let low = json_utf16_unit(&mut chars)?;
+                    char::decode_utf16([unit, low]).next()?.ok()?
+                } else {
+                    char::from_u32(u32::from(unit))?
+                }
+            }
+            _ => return None,
+        });
+    }
+    Some(value)
+}
+
+/// The UTF-16 code unit of the four hex digits of a `\u` escape.
+fn json_utf16_unit(chars: &mut std::str::Chars<'_>) -> Option<u16> {
+    let digits: String = chars.take(4).collect();
+    if digits.len() != 4 {
+        return None;
+    }
+    u16::from_str_radix(&digits, 16).ok()
+}
+
+/// `value` escaped for a JSON string literal, e.g. backslashes as `\\`.
+fn escape_json(value: &str) -> String {
+    let mut literal = String::with_capacity(value.len() + 2);
+    for c in value.chars() {
+        match c {
+            '"' => literal.push_str("\\\""),
+            '\\' => literal.push_str("\\\\"),
+            '\n' => literal.push_str("\\n"),
+            '\r' => literal.push_str("\\r"),
+            '\t' => literal.push_str("\\t"),
+            c if (c as u32) < 0x20 => literal.push_str(&format!("\\u{:04x}", c as u32)),
+            c => literal.push(c),
+        }
+    }
+    literal
+}
+
+/// Translates paths between Windows and WSL for one mount configuration.
+pub struct Translator {
+    mount_root: String,
+    drive_mounts: HashMap<String, String>,
+    unc_distro: Option<String>,
+    separator: WinSeparator,
+    drive_case: DriveCase,
+    relative_paths: RelativePaths,
+    embedded_paths: bool,
+    env_vars: HashMap<String, String>,
+    bind_mounts: Vec<BindMount>,
+    home_mount: Option<BindMount>,
+    wslpath_re: Regex,
+    wslpath_needles: Vec<String>,
+    mounted_re: Regex,
+}
+
+impl Translator {
+    /// A translator for the drives mounted at `drive_mounts`, or else below
+    /// `mount_root`. With a `unc_distro`, other Linux paths are translated to
+    /// `\\wsl$\<unc_distro>\...` paths.
+    pub fn new(mount_root: &str, drive_mounts: HashMap<String, String>,
+               unc_distro: Option<String>) -> Translator {
+        let wslpath_re = wslpath_regex(mount_root, &drive_mounts, unc_distro.is_some());
+        let wslpath_needles = wslpath_needles(mount_root, &drive_mounts, unc_distro.is_some(), &[]);
+        let mounted_re = wslpath_regex(mount_root, &drive_mounts, false);
+        Translator {
+            mount_root: mount_root.to_owned(), drive_mounts, unc_distro,
+            separator: WinSeparator::Slash, drive_case: DriveCase::Lower,
+            relative_paths: RelativePaths::Explicit, embedded_paths: false, env_vars: HashMap::new(),
+            bind_mounts: vec![], home_mount: None, wslpath_re, wslpath_needles, mounted_re,
+        }
+    }
+
+    /// The same translator, translating the Windows directories of the
+    /// `path_map` to the Linux directories they are mapped to, e.g. for
+    /// `C:\repos` bind mounted to `/home/me/repos` inside WSL. Paths which
+    /// are not below any of them are translated as before.
+    pub fn with_path_map(self, path_map: &[(String, String)]) -> Translator {
+        let bind_mounts: Vec<BindMount> = path_map.iter()
+            .filter_map(|(win_dir, unix_dir)| {
+                let mount_path = self.to_unix(win_dir.clone()).ok()?;
+                Some(BindMount {
+                    mount_path: mount_path.trim_end_matches('/').to_owned(),
+                    bind_path: unix_dir.trim_end_matches('/').to_owned(),
+                })
+            })
+            .filter(|bind_mount| !bind_mount.bind_path.is_empty())
+            .collect();
+        let bind_paths: Vec<&str> = bind_mounts.iter()
+            .map(|bind_mount| bind_mount.bind_path.as_str())
+            .collect();
+        let wslpath_re = build_wslpath_regex(
+            &self.mount_root, &self.drive_mounts, self.unc_distro.is_some(), &bind_paths);
+        let wslpath_needles = wslpath_needles(
+            &self.mount_root, &self.drive_mounts, self.unc_distro.is_some(), &bind_paths);
+        Translator { bind_mounts, wslpath_re, wslpath_needles, ..self }
+    }
+
+    /// The same translator, translating the paths below the Windows user
+    /// profile to the same paths below the home directory inside WSL with
+    /// `profile_home`, e.g. `C:\Users\me\.githooks` to `/home/me/.githooks`.
+    /// Unlike the `path_map`, paths in the output are not translated back,
+    /// the two directories do not contain the same files.
+    pub fn with_profile_home(self, profile_home: Option<(String, String)>) -> Translator {
+        let home_mount = profile_home.and_then(|(profile, home)| {
+            let mount_path = translate_to_unix(
+                profile, &|drive| self.mounted_drive_prefix(drive), true, RelativePaths::Explicit,
+                &[]).ok()?;
+            Some(BindMount {
+                mount_path: mount_path.trim_end_matches('/').to_owned(),
+                bind_path: home.trim_end_matches('/').to_owned(),
+            })
+        });
+        Translator { home_mount, ..self }
+    }
+
+    /// The same translator, translating the arguments selected by
+    /// `relative_paths` as relative paths.
+    pub fn with_relative_paths(self, relative_paths: RelativePaths) -> Translator {
+        Translator { relative_paths, ..self }
+    }
+
+    /// The same translator, translating each of the Windows paths embedded
+    /// in an argument with `embedded_paths`, like in `C:\a;C:\b`.
+    pub fn with_embedded_paths(self, embedded_paths: bool) -> Translator {
+        Translator { embedded_paths, ..self }
+    }
+
+    /// The same translator, expanding references like `%USERPROFILE%` to the
+    /// Windows environment variables `vars` in arguments before translating
+    /// them, like `cmd` does.
+    pub fn with_env_vars<I>(self, vars: I) -> Translator
+        where I: IntoIterator<Item=(String, String)> {
+        let env_vars = vars.into_iter().map(|(name, value)| (name.to_uppercase(), value)).collect();
+        Translator { env_vars, ..self }
+    }
+
+    /// The same translator, producing Windows paths with `separator`.
+    pub fn with_separator(self, separator: WinSeparator) -> Translator {
+        Translator { separator, ..self }
+    }
+
+    /// The same translator, mounting the drives below the mount root at
+    /// directories named with `drive_case`.
+    pub fn with_drive_case(self, drive_case: DriveCase) -> Translator {
+        Translator { drive_case, ..self }
+    }
+
+    /// The directory below which drives are mounted, e.g. `/mnt`.
+    pub fn mount_root(&self) -> &str {
+        &self.mount_root
+    }
+
+    /// The drives discovered at other mount points, by lower case letter.
+    pub fn drive_mounts(&self) -> &HashMap<String, String> {
+        &self.drive_mounts
+    }
+
+    /// The Linux directory `drive` is mounted at.
+    pub fn drive_prefix(&self, drive: &str) -> String {
+        match self.drive_mounts.get(&drive.to_ascii_lowercase()) {
+            Some(mount_point) => mount_point.clone(),
+            None => format!("{}/{}", self.mount_root, self.drive_case.apply(drive)),
+        }
+    }
+
+    /// Like `drive_prefix`, but fails for a drive missing among the
+    /// `drive_mounts` if they were discovered with the drives WSL mounts
+    /// below the mount root, so they list all mounted drives.
+    fn mounted_drive_prefix(&self, drive: &str) -> Result<String, Error> {
+        let lists_automounts = self.drive_mounts.iter().any(
+            |(letter, mount_point)| *mount_point == format!("{}/{}", self.mount_root, letter));
+        if lists_automounts && !self.drive_mounts.contains_key(&drive.to_ascii_lowercase()) {
+            return Err(Error::UnmountedDrive(drive.to_ascii_uppercase()));
+        }
+        Ok(self.drive_prefix(drive))
+    }
+
+    /// The bind mounts applied to translated arguments, the ones of the path
+    /// map before the home directory.
+    fn unix_bind_mounts(&self) -> Vec<BindMount> {
+        self.bind_mounts.iter().chain(self.home_mount.iter()).cloned().collect()
+    }
+
+    /// `arg` with the references to the variables of `with_env_vars` expanded.
+    fn expand_env_vars(&self, arg: String) -> String {
+        if self.env_vars.is_empty() || !arg.contains('%') {
+            return arg;
+        }
+        expand_win_env_vars(&arg, &self.env_vars)
+    }
+
+    /// Translate `arg` like `translate_to_unix`, after expanding the variables
+    /// if `arg` is translated as a path. Other arguments are kept as they are.
+    fn translate_arg(&self, arg: String, is_path: bool) -> Result<String, Error> {
+        let expanded = self.expand_env_vars(arg.clone());
+        let translated = translate_to_unix(
+            expanded.clone(), &|drive| self.mounted_drive_prefix(drive), is_path, self.relative_paths,
+            &self.unix_bind_mounts())?;
+        Ok(if !is_path && translated == expanded { arg } else { translated })
+    }
+
+    /// Translate `arg` to a Linux path if it is an absolute Windows path or a
+    /// relative one selected by `relative_paths`, see `translate_to_unix`.
+    pub fn to_unix(&self, arg: String) -> Result<String, Error> {
+        self.translate_arg(arg, false)
+    }
+
+    /// Translate `arg` to a Linux path like `to_unix`, also if it is relative,
+    /// as for arguments known to be paths.
+    pub fn to_unix_path(&self, arg: String) -> Result<String, Error> {
+        self.translate_arg(arg, true)
+    }
+
+    /// Translate each absolute Windows path in `arg` to a Linux path in place,
+    /// e.g. in a `;` separated list or a command line. Paths containing
+    /// spaces are not found, and untranslatable ones are kept as they are.
+    pub fn to_unix_embedded(&self, arg: &str) -> String {
+        translate_embedded_to_unix(
+            arg, &|drive| self.mounted_drive_prefix(drive), &self.unix_bind_mounts())
+    }
+
+    /// What kind of path `arg` is taken for by `to_unix`, or by `to_unix_path`
+    /// with `is_path`, mirroring the decisions of `translate_to_unix`.
+    pub fn classify(&self, arg: &str, is_path: bool) -> ArgKind {
+        if !is_path && !may_need_translation(arg) {
+            return ArgKind::Skipped;
+        }
+        if let Some((_, path)) = split_absolute_pathspec(arg) {
+            return self.classify(path, true);
+        }
+        let value = if arg.starts_with("--") { arg.split_once('=').map_or(arg, |(_, value)| value) }
+                    else { arg };
+        let value = unquote(value).map_or(value, |(_, inner)| inner);
+        if file_url_win_path(value).is_some() {
+            return ArgKind::FileUrl;
+        }
+        if is_native_unix_path(value) || is_url(value) {
+            return ArgKind::LinuxPathOrUrl;
+        }
+        match split_win_prefix(value) {
+            (Some(WinPrefix::Disk(_)), rest) if rest.starts_with(is_separator) => ArgKind::Drive,
+            (Some(WinPrefix::Disk(_)), _) if is_path => ArgKind::DriveRelative,
+            (Some(WinPrefix::Disk(_)), _) => ArgKind::Skipped,
+            (Some(WinPrefix::Wsl(_)), _) => ArgKind::WslShare,
+            (Some(WinPrefix::Unc(server, share)), _) if is_path || is_unc_share(server, share) =>
+                ArgKind::NetworkShare,
+            (Some(WinPrefix::Unc(..)), _) => ArgKind::Skipped,
+            (None, _) if is_path || is_explicit_relative(value) => ArgKind::Relative,
+            (None, _) if self.relative_paths == RelativePaths::Existing
+                && Path::new(&value.replace('\\', "/")).exists() => ArgKind::Relative,
+            (None, _) if has_embedded_win_paths(arg)
+                && (value.len() != arg.len() || self.embedded_paths) => ArgKind::Embedded,
+            (None, _) => ArgKind::Skipped,
+        }
+    }
+
+    /// Translate all Linux paths in the output `line` to Windows paths.
+    pub fn to_win(&self, line: String) -> String {
+        if !self.may_contain_linux_paths(&line) {
+            return line;
+        }
+        String::from(self.wslpath_re.replace_all(
+            &line, |caps: &Captures| self.win_path_replacement(caps)))
+    }
+
+    /// Translate only the Linux paths below the drive mounts in the output
+    /// `line`, like `/mnt/c/repo`, but neither bind mounted nor other Linux
+    /// paths. For outputs which rarely contain paths, like `git stash list`.
+    pub fn to_win_mounted(&self, line: String) -> String {
+        if !self.may_contain_linux_paths(&line) {
+            return line;
+        }
+        String::from(self.mounted_re.replace_all(
+            &line,
+            |caps: &Captures| win_path_replacement(caps, &self.drive_mounts, None, self.separator)))
+    }
+
+    /// Translate the Linux paths in the JSON string literals of the output
+    /// `line`, escaping the backslashes of the Windows paths. Text outside of
+    /// string literals, like the keys' quotes and numbers, is kept.
+    pub fn to_win_json(&self, line: String) -> String {
+        if !self.may_contain_linux_paths(&line) && !line.contains("\\/") {
+            return line;
+        }
+        translate_json_strings(&line, &|value| self.to_win(value))
+    }
+
+    /// Translate the first Linux path in `arg` to a Windows path.
+    pub fn to_win_path(&self, arg: String) -> String {
+        if !self.may_contain_linux_paths(&arg) {
+            return arg;
+        }
+        String::from(self.wslpath_re.replace(
+            &arg, |caps: &Captures| self.win_path_replacement(caps)))
+    }
+
+    /// Whether `text` may contain a Linux path, false for most output lines.
+    fn may_contain_linux_paths(&self, text: &str) -> bool {
+        self.wslpath_needles.iter().any(|needle| text.contains(needle.as_str()))
+    }
+
+    fn win_path_replacement(&self, caps: &Captures) -> String {
+        if let Some(bind_path) = caps.name("bind") {
+            let (rest, trailing) = split_trailing_punctuation(&caps["bind_rest"]);
+            // only whole directory names, not `/home/me/repos2` for `/home/me/repos`
+            if !rest.is_empty() && !rest.starts_with('/') {
+                return caps[0].to_owned();
+            }
+            let mount_path = self.bind_mounts.iter()
+                .find(|bind_mount| bind_mount.bind_path == bind_path.as_str())
+                .map(|bind_mount| bind_mount.mount_path.as_str())
+                .unwrap_or_default();
+            let mounted = self.to_win_path(format!("{}{}{}", &caps["bind_pre"], mount_path, rest));
+            return format!("{}{}", mounted, trailing);
+        }
+        win_path_replacement(caps, &self.drive_mounts, self.unc_distro.as_deref(), self.separator)
+    }
+}
+
+impl Default for Translator {
+    /// Drives mounted below `/mnt` and no UNC paths, as WSL does by default.
+    fn default() -> Translator {
+        Translator::new(DEFAULT_MOUNT_ROOT, HashMap::new(), None)
+    }
+}
+
+/// Quote `arg` for the Linux shell which `wsl` runs the command line with,
+/// leaving tokens without special characters as they are.
+pub fn shell_escape(arg: String) -> String {
+    let is_safe = |c: char| c.is_ascii_alphanumeric() || "_-+=/.,:@%".contains(c);
+    if !arg.is_empty() && arg.chars().all(is_safe) {
+        return arg;
+    }
+    format!("'{}'", arg.replace('\'', "'\\''"))
+}
+
+/// Split a Windows command line at whitespace, keeping double quoted
+/// parts together and removing the quotes.
+pub fn split_command_line(command_line: &str) -> Vec<String> {
+    let mut parts = vec![];
+    let mut current: Option<String> = None;
+    let mut in_quotes = false;
+    for c in command_line.chars() {
+        if c == '"' {
+            in_quotes = !in_quotes;
+            current.get_or_insert_with(String::new);
+        } else if c.is_whitespace() && !in_quotes {
+            parts.extend(current.take());
+        } else {
+            current.get_or_insert_with(String::new).push(c);
+        }
+    }
+    parts.extend(current);
+    parts
+}
+
+/// The `GIT_EDITOR` for git inside WSL, which runs the Windows `editor`
+/// through `wslgit_cmd win-cmd`.
+pub fn translate_git_editor(wslgit_cmd: &str, editor: &str) -> String {
+    let mut editor_cmd = vec![shell_escape(wslgit_cmd.to_owned()), String::from("win-cmd")];
+    editor_cmd.extend(split_command_line(editor).into_iter().map(shell_escape));
+    editor_cmd.join(" ")
+}
+
+/// The `GIT_SSH_COMMAND` for git inside WSL, with the Windows paths in the
+/// arguments of `ssh_command` translated, e.g. in `-i <key>`, `-F<config>`
+/// or `-o IdentityFile=<key>`.
+pub fn translate_ssh_command(ssh_command: &str, translator: &Translator) -> String {
+    let translate = |path: &str| -> Option<String> {
+        if is_absolute_win_path(path) {
+            translator.to_unix(path.to_owned()).ok()
+        } else {
+            None
+        }
+    };
+    split_command_line(ssh_command).into_iter()
+        .map(|part| {
+            let translated = if let Some(path) = translate(&part) {
+                path
+            } else if let Some(path) = part.strip_prefix('-').and_then(
+                |opt| opt.get(1..)).and_then(translate) {
+                format!("{}{}", &part[..2], path)
+            } else if let Some((key, path)) = part.split_once('=').and_then(
+                |(key, value)| translate(value).map(|path| (key, path))) {
+                format!("{}={}", key, path)
+            } else {
+                part
+            };
+            shell_escape(translated)
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Whether the value of a variable is an absolute Windows path to translate.
+pub fn is_absolute_win_path(value: &str) -> bool {
+    match split_win_prefix(value) {
+        (Some(WinPrefix::Disk(_)), rest) => rest.starts_with(is_separator),
+        (Some(WinPrefix::Wsl(_)), _) => true,
+        _ => false,
+    }
+}
+
+/// Translate the `;` separated list of absolute Windows paths in the value of a
+/// variable to a `:` separated list of Linux paths. None if it is no such list.
+pub fn translate_path_list(value: &str, translator: &Translator) -> Option<String> {
+    let paths: Vec<&str> = value.split(';').filter(|path| !path.is_empty()).collect();
+    if !value.contains(';') || paths.is_empty()
+        || !paths.iter().all(|path| is_absolute_win_path(path)) {
+        return None;
+    }
+    paths.into_iter()
+        .map(|path| translator.to_unix(path.to_owned()).ok())
+        .collect::<Option<Vec<String>>>()
+        .map(|paths| paths.join(":"))
+}
+
+/// Global git options which take their value as a separate argument.
+const GIT_OPTS_WITH_VALUE: &[&str] = &[
+    "-c", "-C", "--git-dir", "--work-tree", "--namespace", "--super-prefix", "--config-env"];
+
+/// Global git options taking a path, which is translated even if it is
+/// relative and does not exist yet.
+const GIT_PATH_OPTS: &[&str] = &["-C", "--git-dir", "--work-tree"];
+
+/// Like `GIT_PATH_OPTS`, the options of subcommands which take a path.
+const SUBCOMMAND_PATH_OPTS: &[(&str, &[&str])] = &[
+    ("init", &["--template", "--separate-git-dir"]),
+    ("clone", &["--template", "--separate-git-dir", "--reference", "--reference-if-able"]),
+    ("apply", &["--directory"]),
+    ("am", &["--directory"]),
+    ("archive", &["-o", "--output"]),
+    ("cat-file", &["--path"]),
+    // a path relative to the git directory
+    ("rev-parse", &["--git-path"]),
+];
+
+/// Plumbing commands whose arguments after `--` are all paths, like the
+/// `<pathname>...` of `git check-attr <attr>... -- <pathname>...`.
+const PATHS_AFTER_DASHES_CMDS: &[&str] = &["check-attr", "check-ignore"];
+
+/// Commands whose arguments after `--` are pathspecs, paths which may start
+/// with magic like `:(glob)` or `:!`.
+const PATHSPECS_AFTER_DASHES_CMDS: &[&str] = &[
+    "add", "checkout", "rm", "mv", "restore", "reset", "commit", "stash", "clean", "status", "log",
+    "show", "diff", "blame", "grep", "ls-files"];
+
+/// How `translate_git_args` treats an argument of git.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GitArgRole {
+    /// Always translated as a path, like the value of `-C`.
+    Path,
+    /// A `key=value` configuration, like the value of `-c`.
+    Config,
+    /// A `--config=key=value` option of `git clone`.
+    ConfigOption,
+    /// A pathspec after `--`, translated as a path after its magic.
+    Pathspec,
+    /// Translated if it looks like a path.
+    Other,
+}
+
+/// The role of each of the git arguments `args`, where the values of the
+/// global and subcommand path options are always treated as paths.
+pub fn git_arg_roles(args: &[String]) -> Vec<GitArgRole> {
+    let subcommand = find_subcommand(args);
+    let global_end = subcommand.unwrap_or(args.len());
+    let clone_destination = subcommand
+        .filter(|&pos| args[pos] == "clone")
+        .and_then(|pos| find_clone_destination(&args[pos + 1..]).map(|dest| pos + 1 + dest));
+    // the patches of `git apply` and `git am`
+    let patch_files: Vec<usize> = subcommand
+        .filter(|&pos| args[pos] == "apply" || args[pos] == "am")
+        .map(|pos| find_non_options(&args[pos + 1..], PATCH_OPTS_WITH_VALUE).iter()
+            .map(|patch| pos + 1 + patch)
+            .collect())
+        .unwrap_or_default();
+    // the file of `git bundle create <file>` and the other actions
+    let bundle_file = subcommand
+        .filter(|&pos| args[pos] == "bundle")
+        .and_then(|pos| find_non_options(&args[pos + 1..], &[]).get(1).map(|file| pos + 1 + file));
+    // the paths after the `--` of plumbing commands
+    let paths_start = subcommand
+        .filter(|&pos| PATHS_AFTER_DASHES_CMDS.contains(&args[pos].as_str()))
+        .and_then(|pos| args[pos + 1..].iter().position(|arg| arg == "--")
+            .map(|dashes| pos + 2 + dashes));
+    let pathspecs_start = subcommand
+        .filter(|&pos| PATHSPECS_AFTER_DASHES_CMDS.contains(&args[pos].as_str()))
+        .and_then(|pos| args[pos + 1..].iter().position(|arg| arg == "--")
+            .map(|dashes| pos + 2 + dashes));
+    let is_clone_arg = |i: usize| subcommand.is_some_and(|pos| args[pos] == "clone" && i > pos);
+    let subcommand_path_opts = subcommand
+        .and_then(|pos| SUBCOMMAND_PATH_OPTS.iter().find(|&&(name, _)| name == args[pos]))
+        .map_or(&[][..], |&(_, opts)| opts);
+    let path_opts = |i: usize| if i < global_end { GIT_PATH_OPTS } else { subcommand_path_opts };
+    let mut roles = Vec::with_capacity(args.len());
+    let mut is_path_value = false;
+    let mut is_config_value = false;
+    for (i, arg) in args.iter().enumerate() {
+        if is_config_value {
+            roles.push(GitArgRole::Config);
+        } else if arg.starts_with("--config=") && is_clone_arg(i) {
+            roles.push(GitArgRole::ConfigOption);
+        } else {
+            let is_joined_path_opt = path_opts(i).iter().any(
+                |opt| opt.starts_with("--") && arg.starts_with(&format!("{}=", opt)));
+            let is_path = is_path_value || is_joined_path_opt || clone_destination == Some(i)
+                || patch_files.contains(&i) || bundle_file == Some(i)
+                || paths_start.is_some_and(|start| i >= start);
+            roles.push(if is_path {
+                GitArgRole::Path
+            } else if pathspecs_start.is_some_and(|start| i >= start) {
+                GitArgRole::Pathspec
+            } else {
+                GitArgRole::Other
+            });
+        }
+        is_path_value = path_opts(i).contains(&arg.as_str());
+        is_config_value = !is_config_value && (i < global_end && arg == "-c"
+            || is_clone_arg(i) && (arg == "-c" || arg == "--config"));
+    }
+    roles
+}
+
+/// The git arguments `args` translated for WSL, by their `git_arg_roles`.
+pub fn translate_git_args(args: &[String], translator: &Translator)
+                          -> Result<Vec<String>, Error> {
+    args.iter().zip(git_arg_roles(args))
+        .map(|(arg, role)| match role {
+            GitArgRole::Config => translate_config_arg(arg, translator),
+            GitArgRole::ConfigOption => translate_config_arg(&arg["--config=".len()..], translator)
+                .map(|config| format!("--config={}", config)),
+            GitArgRole::Path => translator.translate_arg(arg.clone(), true),
+            GitArgRole::Pathspec => translate_pathspec(arg, translator),
+            GitArgRole::Other if translator.embedded_paths && has_embedded_win_paths(arg) =>
+                Ok(translator.to_unix_embedded(arg)),
+            GitArgRole::Other => translator.translate_arg(arg.clone(), false),
+        })
+        .collect()
+}
+
+/// Whether the path of a pathspec is written for Windows, with a `\\` or a
+/// prefix like `C:`. Others like `dir/` are read by git as they are.
+fn is_win_pathspec(path: &str) -> bool {
+    path.contains('\\') || split_win_prefix(path).0.is_some()
+}
+
+/// Translate the path of a `pathspec` after `--`, keeping its magic and a
+/// trailing separator, which restricts it to directories.
+fn translate_pathspec(pathspec: &str, translator: &Translator) -> Result<String, Error> {
+    let (magic, path) = split_pathspec_magic(pathspec);
+    if !is_win_pathspec(path) {
+        return Ok(pathspec.to_owned());
+    }
+    let mut translated = translator.translate_arg(path.to_owned(), true)?;
+    if path.ends_with(is_separator) && !translated.ends_with('/') {
+        translated.push('/');
+    }
+    Ok(join_pathspec_magic(magic, &translated))
+}
+
+/// The `magic` of a pathspec followed by its translated `path`. The short
+/// form is closed with a `:`, as git would otherwise read the `/` of a Linux
+/// path as more magic, like in `:!/mnt/c/x`.
+fn join_pathspec_magic(magic: &str, path: &str) -> String {
+    if magic.is_empty() || magic.ends_with(')') || magic.len() > 1 && magic.ends_with(':') {
+        format!("{}{}", magic, path)
+    } else {
+        format!("{}:{}", magic, path)
+    }
+}
+
+/// Split the magic off the `pathspec`: the long form like `:(glob,icase)`,
+/// or the short form of `:` with any of `/!^` and an optional closing `:`.
+fn split_pathspec_magic(pathspec: &str) -> (&str, &str) {
+    if !pathspec.starts_with(':') {
+        return ("", pathspec);
+    }
+    let end = if pathspec[1..].starts_with('(') {
+        pathspec.find(')').map_or(pathspec.len(), |close| close + 1)
+    } else {
+        let magic_end = pathspec[1..].find(|c| !"/!^".contains(c)).map_or(pathspec.len(), |pos| pos + 1);
+        if pathspec[magic_end..].starts_with(':') { magic_end + 1 } else { magic_end }
+    };
+    pathspec.split_at(end)
+}
+
+/// The magic and the path of a pathspec with magic and an absolute Windows
+/// path, like `:(exclude)C:\a` or `:/C:\a`. A lone `:` is no magic, as in
+/// the `:<path>` of a blob in the index.
+fn split_absolute_pathspec(arg: &str) -> Option<(&str, &str)> {
+    let (magic, path) = split_pathspec_magic(arg);
+    if magic.len() > 1 && is_absolute_win_path(path) { Some((magic, path)) } else { None }
+}
+
+/// Why an argument is translated or not, as reported with `WSLGIT_VERBOSE`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ArgKind {
+    /// An absolute path on a drive, like `C:\repo`.
+    Drive,
+    /// A path relative to the current directory on a drive, like `C:repo`.
+    DriveRelative,
+    /// A path inside a distro, like `\\wsl$\Ubuntu\home`.
+    WslShare,
+    /// A path on a network share, which cannot be translated.
+    NetworkShare,
+    /// A relative path like `.\src`, or any relative path known to be one.
+    Relative,
+    /// A `file://` URL of a Windows path.
+    FileUrl,
+    /// A Linux path or a URL, passed through.
+    LinuxPathOrUrl,
+    /// Contains Windows paths, like `C:\a;C:\b` or `--format=%H C:\a`.
+    Embedded,
+    /// Anything else, like flags, refs or messages.
+    Skipped,
+}
+
+/// Explain the translation of the git arguments `args` by `translator`: the
+/// role of each and what kind of path it, or the value of a configuration,
+/// is taken for.
+pub fn explain_git_args(args: &[String], translator: &Translator) -> Vec<(GitArgRole, ArgKind)> {
+    args.iter().zip(git_arg_roles(args))
+        .map(|(arg, role)| {
+            let config = match role {
+                GitArgRole::Config => Some(arg.as_str()),
+                GitArgRole::ConfigOption => Some(&arg["--config=".len()..]),
+                _ => None,
+            };
+            let kind = match config.map(|config| config.split_once('=')) {
+                Some(Some((key, value))) if is_path_config(key) => translator.classify(value, true),
+                Some(_) if translator.embedded_paths && has_embedded_win_paths(arg) => ArgKind::Embedded,
+                Some(_) => ArgKind::Skipped,
+                None if role == GitArgRole::Pathspec => match split_pathspec_magic(arg).1 {
+                    path if is_win_pathspec(path) => translator.classify(path, true),
+                    _ => ArgKind::Skipped,
+                },
+                None => translator.classify(arg, role == GitArgRole::Path),
+            };
+            (role, kind)
+        })
+        .collect()
+}
+
+/// Git configuration variables whose values are paths.
+const PATH_CONFIG_VARS: &[&str] = &[
+    "core.hookspath", "core.excludesfile", "core.attributesfile", "core.worktree",
+    "include.path", "commit.template", "init.templatedir"];
+
+/// Whether the git configuration variable `key` takes a path, comparing the
+/// case insensitive section and variable names.
+fn is_path_config(key: &str) -> bool {
+    let key = key.to_ascii_lowercase();
+    PATH_CONFIG_VARS.contains(&key.as_str())
+        || key.starts_with("includeif.") && key.ends_with(".path")
+}
+
+/// Translate the value of a `key=value` configuration argument, like the one
+/// of `-c`, if `key` takes a path.
+fn translate_config_arg(arg: &str, translator: &Translator) -> Result<String, Error> {
+    match arg.split_once('=') {
+        Some((key, value)) if is_path_config(key) => {
+            let value = translator.to_unix_path(value.to_owned())?;
+            Ok(format!("{}={}", key, value))
+        }
+        _ if translator.embedded_paths && has_embedded_win_paths(arg) => {
+            Ok(translator.to_unix_embedded(arg))
+        }
+        _ => Ok(arg.to_owned()),
+    }
+}
+
+/// Options of `git clone` which take their value as a separate argument.
+const CLONE_OPTS_WITH_VALUE: &[&str] = &[
+    "-b", "--branch", "-o", "--origin", "-u", "--upload-pack", "-c", "--config", "-j", "--jobs",
+    "--reference", "--reference-if-able", "--separate-git-dir", "--template", "--depth",
+    "--shallow-since", "--shallow-exclude", "--filter", "--server-option", "--bundle-uri"];
+
+/// Options of `git apply` and `git am` which take their value as a separate
+/// argument. The key id of `-S` or `--gpg-sign` is optional, and only taken
+/// when attached.
+const PATCH_OPTS_WITH_VALUE: &[&str] = &[
+    "-p", "-C", "--directory", "--exclude", "--include", "--whitespace", "--build-fake-ancestor",
+    "--patch-format", "--resolvemsg", "--quoted-cr", "--empty"];
+
+/// Positions of the non-option arguments in the arguments of a subcommand,
+/// where the `opts_with_value` take the next argument as their value.
+fn find_non_options(args: &[String], opts_with_value: &[&str]) -> Vec<usize> {
+    let mut non_options = vec![];
+    let mut options_end = false;
+    let mut i = 0;
+    while i < args.len() {
+        let arg = args[i].as_str();
+        if !options_end && arg == "--" {
+            options_end = true;
+        } else if !options_end && opts_with_value.contains(&arg) {
+            i += 1;
+        } else if options_end || !arg.starts_with('-') {
+            non_options.push(i);
+        }
+        i += 1;
+    }
+    non_options
+}
+
+/// Position of the destination directory in the arguments of `git clone`
+/// after the subcommand, which is the second non-option argument.
+fn find_clone_destination(args: &[String]) -> Option<usize> {
+    find_non_options(args, CLONE_OPTS_WITH_VALUE).get(1).cloned()
+}
+
+/// Position of the git subcommand in `args`, skipping the global options.
+pub fn find_subcommand(args: &[String]) -> Option<usize> {
+    let mut i = 0;
+    while i < args.len() {
+        let arg = args[i].as_str();
+        if GIT_OPTS_WITH_VALUE.contains(&arg) {
+            i += 2;
+        } else if arg.starts_with('-') {
+            i += 1;
+        } else {
+            return Some(i);
+        }
+    }
+    None
+}
+
+
+#[cfg(test)]
+fn args(args: &[&str]) -> Vec<String> {
+    args.iter().map(|arg| arg.to_string()).collect()
+}
+
+#[test]
+fn win_to_unix_path_trans() {
+    assert_eq!(
+        Translator::default().to_unix("d:\\test\\file.txt".to_string()).unwrap(),
+        "/mnt/d/test/file.txt");
+    assert_eq!(
+        Translator::default().to_unix("C:\\Users\\test\\a space.txt".to_string()).unwrap(),
+        "/mnt/c/Users/test/a space.txt");
+}
+
+#[test]
+fn unix_to_win_path_trans() {
+    let translator = Translator::default();
+    assert_eq!(
+        translator.to_win("/mnt/d/some path/a file.md".to_string()),
+        "d:/some path/a file.md".to_string());
+    assert_eq!(
+        translator.to_win("origin  /mnt/c/path/ (fetch)".to_string()),
+        "origin  c:/path/ (fetch)");
+    let multiline = "mirror  /mnt/c/other/ (fetch)\nmirror  /mnt/c/other/ (push)\n";
+    let multiline_result = "mirror  c:/other/ (fetch)\nmirror  c:/other/ (push)\n";
+    assert_eq!(
+        &*translator.to_win(String::from(multiline)),
+        multiline_result);
+}
+
+#[test]
+fn no_path_translation() {
+    let translator = Translator::default();
+    assert_eq!(
+        &*translator.to_win(String::from("/mnt/other/file.sh")),
+        String::from("/mnt/other/file.sh"));
+}
+
+#[test]
+fn relative_path_translation() {
+    assert_eq!(
+        Translator::default().to_unix(".\\src\\main.rs".to_string()).unwrap(),
+        "./src/main.rs");
+}
+
+#[test]
+fn obvious_non_paths_skipped() {
+    for arg in &["3f2a9c0e5b7d4e1f8a6b2c9d0e1f2a3b4c5d6e7f", "--oneline", "-n", "10", "HEAD~2",
+                 "refs/heads/main", "origin/main", "src/main.rs", "--author=Jane Doe", "/home/me"] {
+        assert!(!may_need_translation(arg), "{}", arg);
+        assert_eq!(Translator::default().to_unix(arg.to_string()).unwrap(), *arg);
+    }
+    for arg in &["C:\\repo", "c:/repo", "d:file", "..\\up", ".\\src", "./src", "\\\\wsl$\\Ubuntu\\home",
+                 "--file=C:\\x", "file:///C:/x", "main:src\\lib.rs"] {
+        assert!(may_need_translation(arg), "{}", arg);
+    }
+}
+
+#[test]
+fn relative_paths_by_syntax() {
+    // the tests run in the crate directory, where `src\main.rs` exists
+    let translator = Translator::default();
+    assert_eq!(translator.to_unix("src\\main.rs".to_owned()).unwrap(), "src\\main.rs");
+    assert_eq!(translator.to_unix("src\\missing.rs".to_owned()).unwrap(), "src\\missing.rs");
+    assert_eq!(translator.to_unix(".\\src\\main.rs".to_owned()).unwrap(), "./src/main.rs");
+    assert_eq!(translator.to_unix("--file=..\\notes.txt".to_owned()).unwrap(), "--file=../notes.txt");
+    assert_eq!(translator.to_unix(".gitignore".to_owned()).unwrap(), ".gitignore");
+    let existing_translator = Translator::default().with_relative_paths(RelativePaths::Existing);
+    assert_eq!(existing_translator.to_unix("src\\main.rs".to_owned()).unwrap(), "src/main.rs");
+    assert_eq!(existing_translator.to_unix("src\\missing.rs".to_owned()).unwrap(), "src\\missing.rs");
+}
+
+#[test]
+fn long_argument_path_translation() {
+    assert_eq!(
+        Translator::default().to_unix("--file=C:\\some\\path.txt".to_owned()).unwrap(),
+        "--file=/mnt/c/some/path.txt");
+}
+
+#[test]
+fn at_sign_arguments() {
+    // git reads no `@<file>` arguments, `@` starts revisions like `@{upstream}`
+    let args = args(&["log", "@{upstream}..@", "@~2", "HEAD@{1}", "--pathspec-from-file=C:\\paths.txt"]);
+    assert_eq!(
+        translate_git_args(&args, &Translator::default()).unwrap(),
+        vec!["log", "@{upstream}..@", "@~2", "HEAD@{1}", "--pathspec-from-file=/mnt/c/paths.txt"]);
+}
+
+#[test]
+fn wsl_unc_path_translation() {
+    assert_eq!(
+        Translator::default().to_unix("\\\\wsl$\\Ubuntu\\home\\user\\repo".to_owned()).unwrap(),
+        "/home/user/repo");
+    assert_eq!(
+        Translator::default().to_unix("\\\\wsl.localhost\\Ubuntu\\home\\user\\repo".to_owned()).unwrap(),
+        "/home/user/repo");
+    assert_eq!(
+        Translator::default().to_unix("\\\\?\\UNC\\wsl$\\Ubuntu\\home".to_owned()).unwrap(),
+        "/home");
+    assert_eq!(
+        Translator::default().to_unix("\\\\wsl$\\Ubuntu".to_owned()).unwrap(),
+        "/");
+    assert_eq!(
+        Translator::default().to_unix("--git-dir=\\\\wsl.localhost\\Debian\\srv\\repo.git".to_owned()).unwrap(),
+        "--git-dir=/srv/repo.git");
+}
+
+#[test]
+fn dot_components_translation() {
+    let translator = Translator::default();
+    assert_eq!(translator.to_unix("C:\\a\\..\\b".to_owned()).unwrap(), "/mnt/c/b");
+    assert_eq!(translator.to_unix("C:\\a\\.\\b\\..\\..\\..\\c".to_owned()).unwrap(), "/mnt/c/c");
+    assert_eq!(translator.to_unix("\\\\wsl$\\Ubuntu\\home\\..\\..\\srv".to_owned()).unwrap(), "/srv");
+    assert_eq!(translator.to_unix("--git-dir=D:\\repo\\sub\\..\\.git".to_owned()).unwrap(),
+               "--git-dir=/mnt/d/repo/.git");
+    // relative paths are kept as they are, `..` may cross symlinks inside WSL
+    assert_eq!(translator.to_unix(".\\.\\x".to_owned()).unwrap(), "./x");
+    assert_eq!(translator.to_unix("..\\sibling\\file".to_owned()).unwrap(), "../sibling/file");
+}
+
+#[test]
+fn repeated_and_trailing_separators_translation() {
+    let translator = Translator::default();
+    assert_eq!(translator.to_unix("C:\\dir\\\\sub\\".to_owned()).unwrap(), "/mnt/c/dir/sub");
+    assert_eq!(translator.to_unix("C:/dir//sub/".to_owned()).unwrap(), "/mnt/c/dir/sub");
+    assert_eq!(translator.to_unix("C:\\dir\\/\\sub\\\\".to_owned()).unwrap(), "/mnt/c/dir/sub");
+    assert_eq!(translator.to_unix("--work-tree=D:\\repo\\".to_owned()).unwrap(),
+               "--work-tree=/mnt/d/repo");
+    assert_eq!(translator.to_unix("\\\\wsl$\\Ubuntu\\home\\\\me\\".to_owned()).unwrap(),
+               "/home/me");
+    assert_eq!(translator.to_unix(".\\dir\\\\sub\\".to_owned()).unwrap(), "./dir/sub");
+    assert_eq!(translator.to_unix("C:\\dir\\\\sub\\".to_owned()).unwrap(), "/mnt/c/dir/sub");
+    // only the root of a drive keeps its separator
+    assert_eq!(translator.to_unix("C:\\\\".to_owned()).unwrap(), "/mnt/c/");
+    assert_eq!(Translator::default().with_path_map(&[("C:\\src".to_owned(), "/src".to_owned())])
+                   .to_unix("C:\\src\\\\lib\\".to_owned()).unwrap(),
+               "/src/lib");
+}
+
+#[test]
+fn drive_root_translation() {
+    let translator = Translator::default();
+    assert_eq!(translator.to_unix("C:\\".to_owned()).unwrap(), "/mnt/c/");
+    assert_eq!(translator.to_unix("C:\\\\".to_owned()).unwrap(), "/mnt/c/");
+    assert_eq!(translator.to_unix("C:/".to_owned()).unwrap(), "/mnt/c/");
+    assert_eq!(translator.to_unix("C:\\a\\..".to_owned()).unwrap(), "/mnt/c/");
+    // `C:` is the current directory on the drive, or its root without one
+    assert_eq!(drive_relative_path('C', "", None), "C:\\");
+    assert_eq!(translator.to_unix(drive_relative_path('C', "", None)).unwrap(), "/mnt/c/");
+    assert_eq!(translator.to_unix(drive_relative_path('C', "", Some("C:\\work"))).unwrap(),
+               "/mnt/c/work");
+    assert_eq!(Translator::new("", HashMap::new(), None).to_unix("C:\\".to_owned()).unwrap(), "/c/");
+}
+
+#[test]
+fn forward_slash_path_translation() {
+    assert_eq!(
+        Translator::default().to_unix("C:/Users/test/file.txt".to_owned()).unwrap(),
+        "/mnt/c/Users/test/file.txt");
+    assert_eq!(
+        Translator::default().to_unix("C:\\a/b\\c".to_owned()).unwrap(),
+        "/mnt/c/a/b/c");
+    assert_eq!(drive_relative_path('C', "foo", None), "C:\\foo");
+    assert_eq!(drive_relative_path('C', "foo", Some("C:\\work\\")), "C:\\work\\foo");
+    assert_eq!(
+        Translator::default().to_unix(drive_relative_path('C', "foo", Some("C:\\work"))).unwrap(),
+        "/mnt/c/work/foo");
+}
+
+#[test]
+fn drive_relative_paths_only_for_paths() {
+    let translator = Translator::default();
+    // refspecs, patterns and other values looking like `C:foo`
+    for git_args in &[&["push", "origin", "a:b"][..], &["log", "--grep=x:y"], &["grep", "-e", "a:b"],
+                      &["show", "HEAD:src/main.rs"]] {
+        assert_eq!(translate_git_args(&args(git_args), &translator).unwrap(), args(git_args));
+    }
+    assert_eq!(translator.to_unix("a:b".to_owned()).unwrap(), "a:b");
+    assert!(translator.to_unix_path("C:".to_owned()).unwrap().starts_with("/mnt/c/"));
+    assert_eq!(translate_git_args(&args(&["-C", "D:repo", "status"]), &translator).unwrap()[1]
+                   .split('/').take(3).collect::<Vec<_>>(), ["", "mnt", "d"]);
+}
+
+#[test]
+fn native_path_passthrough() {
+    assert_eq!(
+        Translator::default().to_unix("/home/me/repo".to_owned()).unwrap(),
+        "/home/me/repo");
+    assert_eq!(
+        Translator::default().to_unix("--work-tree=/home/me/repo".to_owned()).unwrap(),
+        "--work-tree=/home/me/repo");
+    assert_eq!(
+        Translator::default().to_unix("/mnt/c/repo".to_owned()).unwrap(),
+        "/mnt/c/repo");
+    assert_eq!(
+        Translator::default().to_unix("\\\\wsl$\\Ubuntu\\home\\me".to_owned()).unwrap(),
+        "/home/me");
+}
+
+#[test]
+fn win_path_prefix() {
+    assert_eq!(split_win_prefix("C:\\a"), (Some(WinPrefix::Disk('C')), "\\a"));
+    assert_eq!(split_win_prefix("\\\\?\\d:\\a"), (Some(WinPrefix::Disk('d')), "\\a"));
+    assert_eq!(split_win_prefix("\\\\srv\\share\\a"), (Some(WinPrefix::Unc("srv", "share")), "\\a"));
+    assert_eq!(split_win_prefix("\\\\WSL$\\Ubuntu\\a"), (Some(WinPrefix::Wsl("Ubuntu")), "\\a"));
+    assert_eq!(split_win_prefix("src\\main.rs"), (None, "src\\main.rs"));
+}
+
+#[test]
+fn posix_shell_escape() {
+    let cases = [
+        ("status", "status"),
+        ("--file=/mnt/c/a.txt", "--file=/mnt/c/a.txt"),
+        ("", "''"),
+        ("a b", "'a b'"),
+        ("it's", "'it'\\''s'"),
+        ("$(rm -rf)", "'$(rm -rf)'"),
+        ("back`tick`", "'back`tick`'"),
+        ("say \"hi\"!", "'say \"hi\"!'"),
+        ("C:\\dir\\*", "'C:\\dir\\*'"),
+        ("line\n", "'line\n'"),
+    ];
+    for &(arg, escaped) in cases.iter() {
+        assert_eq!(shell_escape(arg.to_owned()), escaped, "escaping {:?}", arg);
+    }
+}
+
+#[test]
+fn degenerate_quotes_in_command_line() {
+    // like on Windows, an unterminated quote extends to the end
+    assert_eq!(split_command_line("\""), vec![""]);
+    assert_eq!(split_command_line("\"\""), vec![""]);
+    assert_eq!(split_command_line("\"x"), vec!["x"]);
+    assert_eq!(split_command_line("\"x\""), vec!["x"]);
+    assert_eq!(split_command_line("ed \"a b"), vec!["ed", "a b"]);
+    assert!(split_command_line("").is_empty());
+    assert!(split_command_line("  ").is_empty());
+    assert_eq!(translate_git_editor("/mnt/c/wslgit.exe", "\""), "/mnt/c/wslgit.exe win-cmd ''");
+}
+
+#[test]
+fn git_editor_translation() {
+    let wslgit_cmd = "/mnt/c/Program Files/wslgit/wslgit.exe";
+    assert_eq!(
+        translate_git_editor(wslgit_cmd, "vim"),
+        "'/mnt/c/Program Files/wslgit/wslgit.exe' win-cmd vim");
+    assert_eq!(
+        translate_git_editor(wslgit_cmd, "\"C:\\Program Files\\Editor\\ed.exe\""),
+        "'/mnt/c/Program Files/wslgit/wslgit.exe' win-cmd 'C:\\Program Files\\Editor\\ed.exe'");
+    assert_eq!(
+        translate_git_editor("/mnt/c/wslgit.exe", "code  --wait "),
+        "/mnt/c/wslgit.exe win-cmd code --wait");
+    assert_eq!(
+        translate_git_editor("/mnt/c/wslgit.exe", "C:\\Tools\\ed.exe -n \"\""),
+        "/mnt/c/wslgit.exe win-cmd 'C:\\Tools\\ed.exe' -n ''");
+}
+
+#[test]
+fn ssh_command_key_paths() {
+    assert_eq!(translate_ssh_command("ssh -i C:\\Users\\me\\.ssh\\id_rsa", &Translator::default()),
+               "ssh -i /mnt/c/Users/me/.ssh/id_rsa");
+    assert_eq!(
+        translate_ssh_command("ssh -i \"C:\\Users\\Jane Doe\\.ssh\\id_rsa\" -FD:/ssh/config -o IdentitiesOnly=yes \
+             -o UserKnownHostsFile=C:\\ssh\\known_hosts -p 2222", &Translator::default()),
+        "ssh -i '/mnt/c/Users/Jane Doe/.ssh/id_rsa' -F/mnt/d/ssh/config -o IdentitiesOnly=yes \
+         -o UserKnownHostsFile=/mnt/c/ssh/known_hosts -p 2222");
+    assert_eq!(translate_ssh_command("ssh", &Translator::default()), "ssh");
+}
+
+#[test]
+fn untranslatable_path_error() {
+    match Translator::default().to_unix("\\\\server\\share\\repo".to_owned()) {
+        Err(Error::UnsupportedPath(path)) => assert_eq!(path, "\\\\server\\share\\repo"),
+        result => panic!("unexpected result {:?}", result),
+    }
+    // regexes and other arguments merely starting with `\\`
+    for git_args in &[&["grep", "\\\\bfoo"][..], &["grep", "-e", "\\\\d+\\w"],
+                      &["log", "--grep=\\\\bfix(es)?"]] {
+        assert_eq!(translate_git_args(&args(git_args), &Translator::default()).unwrap(), args(git_args));
+    }
+    assert!(Translator::default().to_unix_path("\\\\bfoo".to_owned()).is_err());
+    assert!(Translator::default().to_unix("\\\\nas-01.local\\repos".to_owned()).is_err());
+    let not_found = Error::Spawn("wsl".to_owned(), io::Error::from(io::ErrorKind::NotFound));
+    assert_eq!(not_found.exit_code(), 127);
+    assert_eq!(not_found.to_string(), "cannot find wsl, make sure WSL is installed");
+    let denied = Error::Spawn("wsl".to_owned(), io::Error::from(io::ErrorKind::PermissionDenied));
+    assert_eq!(denied.exit_code(), 126);
+    assert_eq!(Error::UnsupportedPath("x".to_owned()).exit_code(), 2);
+}
+
+#[test]
+fn unmounted_drive_error() {
+    let drive_mounts: HashMap<String, String> =
+        [("c".to_owned(), "/mnt/c".to_owned()), ("d".to_owned(), "/data".to_owned())].iter().cloned().collect();
+    let translator = Translator::new(DEFAULT_MOUNT_ROOT, drive_mounts, None);
+    assert_eq!(translator.to_unix("D:\\x".to_owned()).unwrap(), "/data/x");
+    match translator.to_unix("--file=z:\\x".to_owned()) {
+        Err(err @ Error::UnmountedDrive(_)) => {
+            assert_eq!(err.to_string(), "the drive Z: is not mounted inside WSL, see 'mount -t drvfs'");
+            assert_eq!(err.exit_code(), 2);
+        }
+        result => panic!("unexpected result {:?}", result),
+    }
+    let args = vec!["-C".to_owned(), "Z:\\repo".to_owned(), "status".to_owned()];
+    assert!(translate_git_args(&args, &translator).is_err());
+    // without discovered mounts below the mount root, every other drive is
+    // expected there
+    assert_eq!(Translator::default().to_unix("Z:\\x".to_owned()).unwrap(), "/mnt/z/x");
+    let drive_mounts: HashMap<String, String> = [("d".to_owned(), "/data".to_owned())].iter().cloned().collect();
+    assert_eq!(Translator::new(DEFAULT_MOUNT_ROOT, drive_mounts, None).to_unix("Z:\\x".to_owned()).unwrap(),
+               "/mnt/z/x");
+    assert_eq!(Translator::default().to_unix("Z:\\x".to_owned()).unwrap(), "/mnt/z/x");
+}
+
+#[test]
+fn non_ascii_path_translation() {
+    assert_eq!(
+        Translator::default().to_unix("C:\\Users\\Пользователь\\repo".to_owned()).unwrap(),
+        "/mnt/c/Users/Пользователь/repo");
+    assert_eq!(
+        Translator::default().to_unix("--file=D:\\データ\\ファイル.txt".to_owned()).unwrap(),
+        "--file=/mnt/d/データ/ファイル.txt");
+    assert_eq!(
+        Translator::default().to_win("origin\t/mnt/c/Users/Пользователь/repo (fetch)".to_owned()),
+        "origin\tc:/Users/Пользователь/repo (fetch)");
+    assert_eq!(shell_escape("/mnt/c/Users/Пользователь".to_owned()), "'/mnt/c/Users/Пользователь'");
+    // not a drive letter
+    assert_eq!(split_win_prefix("П:\\repo"), (None, "П:\\repo"));
+}
+
+#[test]
+fn nul_separated_output_translation() {
+    let translator = Translator::default();
+    assert_eq!(
+        translator.to_win("/mnt/c/a/one\0/mnt/c/b two\0/mnt/d/c\0".to_owned()),
+        "c:/a/one\0c:/b two\0d:/c\0");
+    let unc_translator = Translator::new(DEFAULT_MOUNT_ROOT, HashMap::new(), Some("Ubuntu".to_owned()));
+    assert_eq!(
+        unc_translator.to_win("/home/me\0/mnt/c/x\0".to_owned()),
+        "\\\\wsl$\\Ubuntu\\home\\me\0c:/x\0");
+}
+
+#[test]
+fn path_list_translation() {
+    let translator = Translator::default();
+    assert_eq!(translate_path_list("C:\\repo\\objects;D:\\shared\\objects", &translator),
+               Some("/mnt/c/repo/objects:/mnt/d/shared/objects".to_owned()));
+    assert_eq!(translate_path_list("C:\\a;\\\\wsl$\\Ubuntu\\srv\\b;d:/c;", &translator),
+               Some("/mnt/c/a:/srv/b:/mnt/d/c".to_owned()));
+    assert_eq!(translate_path_list("C:\\a", &translator), None);
+    assert_eq!(translate_path_list("C:\\a;relative", &translator), None);
+    assert_eq!(translate_path_list("a=1;b=2", &translator), None);
+}
+
+#[test]
+fn embedded_paths_translation() {
+    let translator = Translator::default().with_embedded_paths(true);
+    assert_eq!(translator.to_unix_embedded("C:\\a;C:\\b"), "/mnt/c/a;/mnt/c/b");
+    assert_eq!(translator.to_unix_embedded("ssh -i C:\\Users\\me\\key -F d:/ssh/config"),
+               "ssh -i /mnt/c/Users/me/key -F /mnt/d/ssh/config");
+    assert_eq!(translator.to_unix_embedded("\"C:\\tools\\diff.exe\" 'D:\\x'"),
+               "\"/mnt/c/tools/diff.exe\" '/mnt/d/x'");
+    assert_eq!(translator.to_unix_embedded("no paths: a:b, c:d"), "no paths: a:b, c:d");
+    assert!(has_embedded_win_paths("C:\\a;C:\\b"));
+    assert!(has_embedded_win_paths("--exec=run C:\\a"));
+    assert!(!has_embedded_win_paths("C:\\a"));
+    assert!(!has_embedded_win_paths("--file=C:\\a"));
+    assert!(!has_embedded_win_paths("https://host/repo"));
+
+    assert_eq!(translate_git_args(&args(&["-c", "core.sshCommand=ssh -i C:\\key", "fetch"]),
+                                  &translator).unwrap(),
+               args(&["-c", "core.sshCommand=ssh -i /mnt/c/key", "fetch"]));
+    assert_eq!(translate_git_args(&args(&["add", "C:\\a", "--pathspec-from-file=C:\\b"]),
+                                  &translator).unwrap(),
+               args(&["add", "/mnt/c/a", "--pathspec-from-file=/mnt/c/b"]));
+    // only with the mode enabled
+    assert_eq!(translate_git_args(&args(&["-c", "x.y=ssh -i C:\\key", "fetch"]),
+                                  &Translator::default()).unwrap(),
+               args(&["-c", "x.y=ssh -i C:\\key", "fetch"]));
+}
+
+#[test]
+fn rev_parse_output_translation() {
+    let translator = Translator::default();
+    let cases = [
+        // --show-toplevel, --absolute-git-dir
+        ("/mnt/c/repo", "c:/repo"),
+        ("/mnt/c/repo/.git", "c:/repo/.git"),
+        // --git-dir and --git-common-dir print relative paths inside the repository
+        (".git", ".git"),
+        ("../../.git", "../../.git"),
+        ("/mnt/c/repo/.git/worktrees/feature", "c:/repo/.git/worktrees/feature"),
+        // --show-cdup and --show-prefix are always relative
+        ("../../", "../../"),
+        ("", ""),
+        ("src/mnt/c/", "src/mnt/c/"),
+        ("../mnt/c/x", "../mnt/c/x"),
+    ];
+    for &(output, translated) in cases.iter() {
+        assert_eq!(translator.to_win(output.to_owned()), translated, "translating {:?}", output);
+    }
+}
+
+#[test]
+fn rev_parse_git_path() {
+    let translator = Translator::default();
+    assert_eq!(translate_git_args(&args(&["rev-parse", "--git-path", "hooks"]), &translator).unwrap(),
+               args(&["rev-parse", "--git-path", "hooks"]));
+    assert_eq!(
+        translate_git_args(&args(&["-C", "C:\\repo", "rev-parse", "--git-path", "hooks\\pre-commit"]),
+                           &translator).unwrap(),
+        args(&["-C", "/mnt/c/repo", "rev-parse", "--git-path", "hooks/pre-commit"]));
+    // relative inside the repository, or absolute in a worktree or with `core.hooksPath`
+    for &(output, translated) in [(".git/hooks", ".git/hooks"), (".git/objects", ".git/objects"),
+                                  ("/mnt/c/repo/.git/hooks", "c:/repo/.git/hooks"),
+                                  ("/mnt/d/shared hooks", "d:/shared hooks"),
+                                  ("/home/me/hooks", "/home/me/hooks")].iter() {
+        assert_eq!(translator.to_win(output.to_owned()), translated, "translating {:?}", output);
+    }
+}
+
+#[test]
+fn config_and_check_ignore_output_translation() {
+    let translator = Translator::default();
+    assert_eq!(
+        translator.to_win("file:/mnt/c/Users/me/.gitconfig\tuser.name=Jane Doe".to_owned()),
+        "file:c:/Users/me/.gitconfig\tuser.name=Jane Doe");
+    assert_eq!(
+        translator.to_win("file:.git/config\tremote.origin.url=/mnt/d/origin.git".to_owned()),
+        "file:.git/config\tremote.origin.url=d:/origin.git");
+    assert_eq!(
+        translator.to_win("/mnt/c/Users/me/.config/git/ignore:3:*.log\tbuild/out.log".to_owned()),
+        "c:/Users/me/.config/git/ignore:3:*.log\tbuild/out.log");
+    assert_eq!(
+        translator.to_win(".gitignore:12:/mnt/\tmnt/c/file".to_owned()),
+        ".gitignore:12:/mnt/\tmnt/c/file");
+}
+
+#[test]
+fn trailing_punctuation_translation() {
+    let translator = Translator::default();
+    assert_eq!(translator.to_win("see (/mnt/c/repo/file.txt)".to_owned()), "see (c:/repo/file.txt)");
+    assert_eq!(translator.to_win("[/mnt/c/repo/a], (/mnt/d/)".to_owned()), "[c:/repo/a], (d:/)");
+    assert_eq!(translator.to_win("/mnt/c/a, /mnt/c/b; /mnt/c/c".to_owned()), "c:/a, c:/b; c:/c");
+    assert_eq!(translator.to_win("origin  /mnt/c/path/ (fetch)".to_owned()), "origin  c:/path/ (fetch)");
+    // brackets belonging to the path are kept
+    assert_eq!(translator.to_win("/mnt/c/backup(1)/x[2]".to_owned()), "c:/backup(1)/x[2]");
+    assert_eq!(translator.to_win("(/mnt/c/backup(1))".to_owned()), "(c:/backup(1))");
+    let unc_translator = Translator::new(DEFAULT_MOUNT_ROOT, HashMap::new(), Some("Ubuntu".to_owned()));
+    assert_eq!(unc_translator.to_win("/home/me/a, /home/me/b;".to_owned()),
+               "\\\\wsl$\\Ubuntu\\home\\me\\a, \\\\wsl$\\Ubuntu\\home\\me\\b;");
+    let path_map = vec![("C:\\repos".to_owned(), "/home/me/repos".to_owned())];
+    assert_eq!(Translator::default().with_path_map(&path_map).to_win("(/home/me/repos)".to_owned()),
+               "(c:/repos)");
+}
+
+#[test]
+fn show_origin_output_translation() {
+    let translator = Translator::default();
+    assert_eq!(
+        translator.to_win("file:/mnt/c/Users/me/.gitconfig\tcore.editor=/mnt/c/Tools/ed.exe".to_owned()),
+        "file:c:/Users/me/.gitconfig\tcore.editor=c:/Tools/ed.exe");
+    assert_eq!(
+        translator.to_win("file:/mnt/c/Users/me/.gitconfig\0user.name\nJane\0".to_owned()),
+        "file:c:/Users/me/.gitconfig\0user.name\nJane\0");
+    for line in &["blob:HEAD:.gitmodules\tsubmodule.lib.path=lib",
+                  "blob:1a2b3c4d\tsubmodule.x.url=https://host/x",
+                  "command line:\tcore.hooksPath=hooks",
+                  "standard input:\tuser.name=Jane Doe",
+                  "file:.git/config\tcore.bare=false",
+                  "file:/etc/gitconfig\tcore.autocrlf=input"] {
+        assert_eq!(translator.to_win(line.to_string()), *line);
+    }
+}
+
+#[test]
+fn worktree_list_output_translation() {
+    let translator = Translator::default();
+    assert_eq!(
+        translator.to_win("/mnt/c/repos/thing        1a2b3c4 [main]".to_owned()),
+        "c:/repos/thing        1a2b3c4 [main]");
+    assert_eq!(
+        translator.to_win("/mnt/d/trees/fix bug     5d6e7f8 (detached HEAD) locked".to_owned()),
+        "d:/trees/fix bug     5d6e7f8 (detached HEAD) locked");
+    assert_eq!(
+        translator.to_win("/home/me/linux-tree      9a8b7c6 [topic]".to_owned()),
+        "/home/me/linux-tree      9a8b7c6 [topic]");
+    assert_eq!(
+        translator.to_win("worktree /mnt/c/repos/thing".to_owned()),
+        "worktree c:/repos/thing");
+    assert_eq!(
+        translator.to_win("worktree /mnt/c/repos/thing\0HEAD 1a2b3c4\0branch refs/heads/main\0\0\
+                           worktree /mnt/d/trees/fix bug\0HEAD 5d6e7f8\0detached\0\0".to_owned()),
+        "worktree c:/repos/thing\0HEAD 1a2b3c4\0branch refs/heads/main\0\0\
+         worktree d:/trees/fix bug\0HEAD 5d6e7f8\0detached\0\0");
+}
+
+#[test]
+fn ls_files_output_translation() {
+    let translator = Translator::default();
+    assert_eq!(translator.to_win("/mnt/c/repos/thing/src/main.rs".to_owned()), "c:/repos/thing/src/main.rs");
+    assert_eq!(translator.to_win("/mnt/c/repos/thing/a file.txt".to_owned()), "c:/repos/thing/a file.txt");
+    // relative paths are left untouched, even if they contain a mount point
+    for path in &["src/main.rs", "docs/mnt/c/notes.md", "mnt/c/file", "../mnt/c/file"] {
+        assert_eq!(translator.to_win(path.to_string()), *path);
+    }
+    assert_eq!(
+        translator.to_win("100644 1a2b3c4d 0\t/mnt/d/repo/src/lib.rs".to_owned()),
+        "100644 1a2b3c4d 0\td:/repo/src/lib.rs");
+    assert_eq!(
+        translator.to_win("/mnt/c/repo/a b.txt\0src/lib.rs\0/mnt/c/repo/c\0".to_owned()),
+        "c:/repo/a b.txt\0src/lib.rs\0c:/repo/c\0");
+}
+
+#[test]
+fn env_var_expansion() {
+    let vars = vec![("USERPROFILE".to_owned(), "C:\\Users\\me".to_owned()),
+                    ("Repos".to_owned(), "D:\\repos".to_owned())];
+    let translator = Translator::default().with_env_vars(vars);
+    let to_unix = |arg: &str| translator.to_unix(arg.to_owned()).unwrap();
+    assert_eq!(to_unix("%USERPROFILE%\\x"), "/mnt/c/Users/me/x");
+    assert_eq!(to_unix("%userprofile%\\.gitconfig"), "/mnt/c/Users/me/.gitconfig");
+    assert_eq!(to_unix("--file=%REPOS%\\a"), "--file=/mnt/d/repos/a");
+    // unknown variables and arguments which are no paths are left as they are
+    assert_eq!(to_unix("%FOO%\\x"), "%FOO%\\x");
+    assert_eq!(to_unix("100%"), "100%");
+    assert_eq!(to_unix("50% of %USERPROFILE%"), "50% of %USERPROFILE%");
+    assert_eq!(expand_win_env_vars("%FOO%%USERPROFILE%%x", &translator.env_vars), "%FOO%C:\\Users\\me%x");
+    assert_eq!(expand_win_env_vars("%%", &translator.env_vars), "%%");
+
+    assert_eq!(translate_git_args(&args(&["-C", "%REPOS%\\thing", "commit", "-m", "hi %USERPROFILE%"]), &translator)
+                   .unwrap(),
+               args(&["-C", "/mnt/d/repos/thing", "commit", "-m", "hi %USERPROFILE%"]));
+    // only if enabled
+    assert_eq!(Translator::default().to_unix("%USERPROFILE%\\x".to_owned()).unwrap(), "%USERPROFILE%\\x");
+}
+
+#[test]
+fn quoted_and_embedded_value_translation() {
+    let translator = Translator::default();
+    let to_unix = |arg: &str| translator.to_unix(arg.to_owned()).unwrap();
+    assert_eq!(to_unix("\"C:\\Program Files\\Git\""), "\"/mnt/c/Program Files/Git\"");
+    assert_eq!(to_unix("--file='C:\\my files\\a.txt'"), "--file='/mnt/c/my files/a.txt'");
+    assert_eq!(to_unix("\".\\some dir\\x\""), "\"./some dir/x\"");
+    assert_eq!(to_unix("\"not a path\""), "\"not a path\"");
+    assert_eq!(to_unix("\"C:\\unterminated"), "\"C:\\unterminated");
+    assert_eq!(to_unix("\""), "\"");
+    assert_eq!(to_unix("--pretty=format:%H %cd C:\\path"), "--pretty=format:%H %cd /mnt/c/path");
+    assert_eq!(to_unix("--format=%s (D:\\x\\y)"), "--format=%s (/mnt/d/x/y)");
+    // only in the value of an option
+    assert_eq!(to_unix("see C:\\path"), "see C:\\path");
+    assert_eq!(to_unix("--pretty=format:%H %cd"), "--pretty=format:%H %cd");
+}
+
+#[test]
+fn submodule_output_translation() {
+    let translator = Translator::default();
+    // the names of submodules are relative to the superproject
+    for line in &[" 1a2b3c4d5e6f lib/mnt/c/vendored (v1.0)", "+1a2b3c4d5e6f docs (heads/main)",
+                  "-1a2b3c4d5e6f mnt/c/missing", "Entering 'lib/sub'"] {
+        assert_eq!(translator.to_win(line.to_string()), *line);
+    }
+    assert_eq!(translator.to_win(" 1a2b3c4d5e6f /mnt/c/repos/shared (v1.0-2-g1a2b3c4)".to_owned()),
+               " 1a2b3c4d5e6f c:/repos/shared (v1.0-2-g1a2b3c4)");
+    // `submodule foreach --recursive 'echo $toplevel/$sm_path'`
+    assert_eq!(translator.to_win("Entering 'lib/sub'\n/mnt/c/repos/thing/lib/sub\n".to_owned()),
+               "Entering 'lib/sub'\nc:/repos/thing/lib/sub\n");
+    assert_eq!(translator.to_win("fatal: not a git repository: /mnt/c/repos/thing/.git/modules/lib".to_owned()),
+               "fatal: not a git repository: c:/repos/thing/.git/modules/lib");
+}
+
+#[test]
+fn status_output_translation() {
+    let translator = Translator::default();
+    // the paths of `--porcelain` and `--short` are relative to the repository
+    for line in &[" M src/main.rs", "?? mnt/c/notes.md", "R  old.txt -> docs/mnt/c/new.txt",
+                  "## main...origin/main [ahead 1]"] {
+        assert_eq!(translator.to_win(line.to_string()), *line);
+    }
+    // `--porcelain=v2`
+    for line in &["# branch.oid 1a2b3c4d", "# branch.head main",
+                  "1 .M N... 100644 100644 100644 1a2b3c4d 1a2b3c4d src/lib.rs",
+                  "2 R. N... 100644 100644 100644 1a2b 1a2b R100 new.txt\told.txt",
+                  "? mnt/c/untracked"] {
+        assert_eq!(translator.to_win(line.to_string()), *line);
+    }
+    // `-z`, where only absolute paths are translated
+    assert_eq!(translator.to_win(" M src/main.rs\0R  new.txt\0mnt/c/old.txt\0?? /mnt/c/x\0".to_owned()),
+               " M src/main.rs\0R  new.txt\0mnt/c/old.txt\0?? c:/x\0");
+    assert_eq!(translator.to_win("2 R. N... 100644 100644 100644 1a2b 1a2b R100 a b.txt\0mnt/c/a\0".to_owned()),
+               "2 R. N... 100644 100644 100644 1a2b 1a2b R100 a b.txt\0mnt/c/a\0");
+}
+
+#[test]
+fn git_path_options() {
+    assert_eq!(
+        translate_git_args(&args(&["-C", "sub\\repo", "--git-dir", "..\\missing\\.git",
+                                   "--work-tree", "D:\\tree", "status"]), &Translator::default()).unwrap(),
+        args(&["-C", "sub/repo", "--git-dir", "../missing/.git", "--work-tree", "/mnt/d/tree", "status"]));
+    assert_eq!(
+        translate_git_args(&args(&["--git-dir=missing\\.git", "--work-tree=C:\\tree", "log"]), &Translator::default()).unwrap(),
+        args(&["--git-dir=missing/.git", "--work-tree=/mnt/c/tree", "log"]));
+    // only the global options take paths
+    assert_eq!(
+        translate_git_args(&args(&["grep", "-C", "some\\pattern"]), &Translator::default()).unwrap(),
+        args(&["grep", "-C", "some\\pattern"]));
+}
+
+#[test]
+fn subcommand_path_options() {
+    assert_eq!(
+        translate_git_args(&args(&["init", "--template=C:\\tpl", "--separate-git-dir", "..\\gd", "repo"]),
+                           &Translator::default()).unwrap(),
+        args(&["init", "--template=/mnt/c/tpl", "--separate-git-dir", "../gd", "repo"]));
+    assert_eq!(
+        translate_git_args(&args(&["init", "--template", "missing\\tpl", "--separate-git-dir=missing\\gd"]),
+                           &Translator::default()).unwrap(),
+        args(&["init", "--template", "missing/tpl", "--separate-git-dir=missing/gd"]));
+    assert_eq!(
+        translate_git_args(&args(&["clone", "--separate-git-dir=..\\gd", "--template", "tpl\\dir",
+                                   "--reference", "C:\\ref", "--reference-if-able=ref\\other",
+                                   "git@host:repo", "dest"]), &Translator::default()).unwrap(),
+        args(&["clone", "--separate-git-dir=../gd", "--template", "tpl/dir", "--reference", "/mnt/c/ref",
+               "--reference-if-able=ref/other", "git@host:repo", "dest"]));
+    // the options of other subcommands are not paths
+    assert_eq!(
+        translate_git_args(&args(&["log", "--template=some\\thing"]), &Translator::default()).unwrap(),
+        args(&["log", "--template=some\\thing"]));
+}
+
+#[test]
+fn plumbing_paths() {
+    assert_eq!(
+        translate_git_args(&args(&["check-attr", "diff", "text", "--", "src\\main.rs", "C:\\repo\\a b"]),
+                           &Translator::default()).unwrap(),
+        args(&["check-attr", "diff", "text", "--", "src/main.rs", "/mnt/c/repo/a b"]));
+    assert_eq!(
+        translate_git_args(&args(&["check-ignore", "-v", "--", "build\\out"]), &Translator::default()).unwrap(),
+        args(&["check-ignore", "-v", "--", "build/out"]));
+    assert_eq!(
+        translate_git_args(&args(&["cat-file", "--filters", "--path=src\\lib.rs", "HEAD:src/lib.rs"]),
+                           &Translator::default()).unwrap(),
+        args(&["cat-file", "--filters", "--path=src/lib.rs", "HEAD:src/lib.rs"]));
+    // attributes before `--` are no paths
+    assert_eq!(
+        translate_git_args(&args(&["check-attr", "a\\b", "C:\\repo\\x"]), &Translator::default()).unwrap(),
+        args(&["check-attr", "a\\b", "/mnt/c/repo/x"]));
+    // the output of `check-attr` repeats the paths
+    assert_eq!(Translator::default().to_win("/mnt/c/repo/a b: diff: set".to_owned()), "c:/repo/a b: diff: set");
+    assert_eq!(Translator::default().to_win("src/main.rs: text: auto".to_owned()), "src/main.rs: text: auto");
+}
+
+#[test]
+fn pathspecs_after_dashes() {
+    let translator = Translator::default();
+    assert_eq!(
+        translate_git_args(&args(&["add", "--", "C:\\repo\\file", "src\\main.rs", "*.rs"]), &translator)
+            .unwrap(),
+        args(&["add", "--", "/mnt/c/repo/file", "src/main.rs", "*.rs"]));
+    assert_eq!(
+        translate_git_args(&args(&["log", "--oneline", "--", ":(glob)C:\\repo\\**\\*.rs",
+                                   ":!docs\\old", ":(top,icase)src"]), &translator).unwrap(),
+        args(&["log", "--oneline", "--", ":(glob)/mnt/c/repo/**/*.rs", ":!:docs/old", ":(top,icase)src"]));
+    // the short magic is closed, so the `/` of the path is no magic
+    assert_eq!(
+        translate_git_args(&args(&["add", "--", ":!C:\\x", ":/C:\\repo\\src", ":C:\\y"]), &translator).unwrap(),
+        args(&["add", "--", ":!:/mnt/c/x", ":/:/mnt/c/repo/src", "::/mnt/c/y"]));
+    assert_eq!(
+        translate_git_args(&args(&["-C", "C:\\repo", "rm", "--cached", "--", ":/:build\\out", ":"]), &translator)
+            .unwrap(),
+        args(&["-C", "/mnt/c/repo", "rm", "--cached", "--", ":/:build/out", ":"]));
+    // relative pathspecs without backslashes are left to git, like `dir/`
+    assert_eq!(
+        translate_git_args(&args(&["add", "--", "dir/", "./a//b", ":(glob)src/**/", "src\\sub\\",
+                                   "C:\\repo\\dir\\"]), &translator).unwrap(),
+        args(&["add", "--", "dir/", "./a//b", ":(glob)src/**/", "src/sub/", "/mnt/c/repo/dir/"]));
+    // before `--` and for other commands the arguments are translated as before
+    assert_eq!(
+        translate_git_args(&args(&["checkout", "main", "src\\x", "--", "src\\x"]), &translator).unwrap(),
+        args(&["checkout", "main", "src\\x", "--", "src/x"]));
+    assert_eq!(
+        translate_git_args(&args(&["rev-parse", "--", "a\\b"]), &translator).unwrap(),
+        args(&["rev-parse", "--", "a\\b"]));
+
+    assert_eq!(split_pathspec_magic(":(exclude)a"), (":(exclude)", "a"));
+    assert_eq!(split_pathspec_magic(":^a"), (":^", "a"));
+    assert_eq!(split_pathspec_magic(":!:a"), (":!:", "a"));
+    assert_eq!(split_pathspec_magic("a:b"), ("", "a:b"));
+    assert_eq!(explain_git_args(&args(&["add", "--", ":!C:\\x"]), &translator)[2],
+               (GitArgRole::Pathspec, ArgKind::Drive));
+}
+
+#[test]
+fn patch_paths() {
+    assert_eq!(
+        translate_git_args(&args(&["apply", "--directory=sub\\tree", "-p", "2", "fixes\\a.diff", "C:\\b.diff"]),
+                           &Translator::default()).unwrap(),
+        args(&["apply", "--directory=sub/tree", "-p", "2", "fixes/a.diff", "/mnt/c/b.diff"]));
+    assert_eq!(
+        translate_git_args(&args(&["apply", "--directory", "sub\\tree", "--check", "a.diff"]),
+                           &Translator::default()).unwrap(),
+        args(&["apply", "--directory", "sub/tree", "--check", "a.diff"]));
+    assert_eq!(
+        translate_git_args(&args(&["am", "--directory", "lib\\x", "-3", "--", "-odd.patch", "mails\\0001.patch"]),
+                           &Translator::default()).unwrap(),
+        args(&["am", "--directory", "lib/x", "-3", "--", "-odd.patch", "mails/0001.patch"]));
+    assert_eq!(
+        translate_git_args(&args(&["am", "-S", "C:\\p\\0001.patch", "-SABCD", "--gpg-sign", "p\\0002.patch"]),
+                           &Translator::default()).unwrap(),
+        args(&["am", "-S", "/mnt/c/p/0001.patch", "-SABCD", "--gpg-sign", "p/0002.patch"]));
+    assert_eq!(
+        translate_git_args(&args(&["log", "--directory", "sub\\tree"]), &Translator::default()).unwrap(),
+        args(&["log", "--directory", "sub\\tree"]));
+}
+
+#[test]
+fn output_file_paths() {
+    assert_eq!(
+        translate_git_args(&args(&["bundle", "create", "C:\\out.bundle", "main"]), &Translator::default()).unwrap(),
+        args(&["bundle", "create", "/mnt/c/out.bundle", "main"]));
+    assert_eq!(
+        translate_git_args(&args(&["bundle", "create", "-q", "backups\\new.bundle", "--all"]),
+                           &Translator::default()).unwrap(),
+        args(&["bundle", "create", "-q", "backups/new.bundle", "--all"]));
+    assert_eq!(
+        translate_git_args(&args(&["bundle", "verify", "..\\in.bundle"]), &Translator::default()).unwrap(),
+        args(&["bundle", "verify", "../in.bundle"]));
+    assert_eq!(
+        translate_git_args(&args(&["archive", "-o", "C:\\out.tar", "HEAD"]), &Translator::default()).unwrap(),
+        args(&["archive", "-o", "/mnt/c/out.tar", "HEAD"]));
+    assert_eq!(
+        translate_git_args(&args(&["archive", "--output=dist\\src.zip", "HEAD", "src"]),
+                           &Translator::default()).unwrap(),
+        args(&["archive", "--output=dist/src.zip", "HEAD", "src"]));
+}
+
+#[test]
+fn path_config_values() {
+    assert_eq!(
+        translate_git_args(&args(&["-c", "core.hooksPath=C:\\h", "-c", "include.path=C:\\g.cfg", "commit"]),
+                           &Translator::default()).unwrap(),
+        args(&["-c", "core.hooksPath=/mnt/c/h", "-c", "include.path=/mnt/c/g.cfg", "commit"]));
+    assert_eq!(
+        translate_git_args(&args(&["-c", "includeIf.gitdir:C:/work/.path=D:\\work.cfg", "status"]),
+                           &Translator::default()).unwrap(),
+        args(&["-c", "includeIf.gitdir:C:/work/.path=/mnt/d/work.cfg", "status"]));
+    // other values are left as they are, and `-c` of subcommands is no config
+    assert_eq!(
+        translate_git_args(&args(&["-c", "user.name=C:\\me", "-c", "core.hooksPath=hooks\\dir", "log"]),
+                           &Translator::default()).unwrap(),
+        args(&["-c", "user.name=C:\\me", "-c", "core.hooksPath=hooks/dir", "log"]));
+    assert_eq!(
+        translate_git_args(&args(&["grep", "-c", "core.hooksPath=C:\\h"]), &Translator::default()).unwrap(),
+        args(&["grep", "-c", "core.hooksPath=C:\\h"]));
+    assert_eq!(
+        translate_git_args(&args(&["clone", "-c", "core.hooksPath=C:\\h", "--config=include.path=C:\\g.cfg",
+                                   "git@host:repo", "dest"]), &Translator::default()).unwrap(),
+        args(&["clone", "-c", "core.hooksPath=/mnt/c/h", "--config=include.path=/mnt/c/g.cfg",
+               "git@host:repo", "dest"]));
+}
+
+#[test]
+fn clone_urls_and_destination() {
+    for url in &["https://host/repo", "HTTP://host/repo", "ssh://git@host:22/repo", "git://host/repo",
+                 "file:///home/me/repo", "git@host:repo", "user@host:C:\\repo"] {
+        assert!(is_url(url), "{}", url);
+        assert_eq!(Translator::default().to_unix(url.to_string()).unwrap(), *url);
+        assert_eq!(
+            translate_git_args(&args(&["clone", url, "C:\\dest"]), &Translator::default()).unwrap(),
+            args(&["clone", url, "/mnt/c/dest"]));
+    }
+    for path in &["C:\\repo", "c:/repo", "repo", "HEAD:src", "\\\\wsl$\\Ubuntu\\repo"] {
+        assert!(!is_url(path), "{}", path);
+    }
+    assert_eq!(
+        translate_git_args(&args(&["clone", "--depth", "1", "-b", "main", "git@host:repo", "new\\dest"]), &Translator::default()).unwrap(),
+        args(&["clone", "--depth", "1", "-b", "main", "git@host:repo", "new/dest"]));
+    assert_eq!(
+        translate_git_args(&args(&["clone", "--", "https://host/repo", "-dest"]), &Translator::default())
+            .unwrap(),
+        args(&["clone", "--", "https://host/repo", "-dest"]));
+    assert_eq!(find_clone_destination(&args(&["https://host/repo"])), None);
+}
+
+#[test]
+fn file_url_round_trip() {
+    let translator = Translator::default();
+    assert_eq!(
+        Translator::default().to_unix("file:///C:/repos/thing".to_owned()).unwrap(),
+        "file:///mnt/c/repos/thing");
+    assert_eq!(
+        Translator::default().to_unix("FILE://d:\\repos\\thing".to_owned()).unwrap(),
+        "file:///mnt/d/repos/thing");
+    assert_eq!(
+        Translator::default().to_unix("file:///home/me/repo".to_owned()).unwrap(),
+        "file:///home/me/repo");
+    assert_eq!(
+        translator.to_win("origin\tfile:///mnt/c/repos/thing (fetch)".to_owned()),
+        "origin\tfile:///c:/repos/thing (fetch)");
+}
+
+#[test]
+fn remote_output_translation() {
+    let translator = Translator::default();
+    // remote get-url
+    assert_eq!(translator.to_win("file:///mnt/c/repos/thing.git".to_owned()), "file:///c:/repos/thing.git");
+    assert_eq!(translator.to_win("/mnt/d/repos/thing.git".to_owned()), "d:/repos/thing.git");
+    assert_eq!(translator.to_win("git@host:repos/thing.git".to_owned()), "git@host:repos/thing.git");
+    // remote show
+    let remote_show = ["* remote origin",
+                       "  Fetch URL: file:///mnt/c/repos/thing.git",
+                       "  Push  URL: /mnt/c/repos/thing.git",
+                       "  HEAD branch: main",
+                       "  Remote branch:",
+                       "    main tracked"];
+    let translated: Vec<String> = remote_show.iter().map(|line| translator.to_win(line.to_string())).collect();
+    assert_eq!(translated, vec!["* remote origin",
+                                "  Fetch URL: file:///c:/repos/thing.git",
+                                "  Push  URL: c:/repos/thing.git",
+                                "  HEAD branch: main",
+                                "  Remote branch:",
+                                "    main tracked"]);
+}
+
+#[test]
+fn backslash_separator_translation() {
+    let translator = Translator::default().with_separator(WinSeparator::Backslash);
+    assert_eq!(translator.to_win_path("/mnt/c/Users/me".to_owned()), "c:\\Users\\me");
+    assert_eq!(translator.to_win("/mnt/c/".to_owned()), "c:\\");
+    assert_eq!(
+        translator.to_win("origin\t/mnt/d/repos/a b.git (fetch) refs/heads/main".to_owned()),
+        "origin\td:\\repos\\a b.git (fetch) refs/heads/main");
+    assert_eq!(
+        translator.to_win("file:/mnt/c/x/.gitconfig\tremote.origin.url=file:///mnt/c/repos/y".to_owned()),
+        "file:c:\\x\\.gitconfig\tremote.origin.url=file:///c:/repos/y");
+    assert_eq!(translator.to_win("src/lib.rs /home/me".to_owned()), "src/lib.rs /home/me");
+    let slash_translator = Translator::default().with_separator(WinSeparator::Slash);
+    assert_eq!(slash_translator.to_win("/mnt/c/Users/me src/a".to_owned()), "c:/Users/me src/a");
+}
+
+#[test]
+fn path_map_translation() {
+    let path_map = vec![("C:\\Repos\\".to_owned(), "/home/me/repos".to_owned()),
+                        ("D:\\data".to_owned(), "/srv/data/".to_owned())];
+    let translator = Translator::default().with_path_map(&path_map);
+    // hitting an override
+    assert_eq!(translator.to_unix("C:\\repos\\thing".to_owned()).unwrap(), "/home/me/repos/thing");
+    assert_eq!(translator.to_unix("c:/Repos".to_owned()).unwrap(), "/home/me/repos");
+    assert_eq!(translator.to_unix("--git-dir=D:\\data\\x.git".to_owned()).unwrap(), "--git-dir=/srv/data/x.git");
+    assert_eq!(translator.to_win("/home/me/repos/thing/.git".to_owned()), "c:/Repos/thing/.git");
+    assert_eq!(translator.to_win("origin\tfile:///srv/data/x.git (fetch)".to_owned()),
+               "origin\tfile:///d:/data/x.git (fetch)");
+    assert_eq!(translator.to_win("/home/me/repos".to_owned()), "c:/Repos");
+    // missing the overrides
+    assert_eq!(translator.to_unix("C:\\repos2\\thing".to_owned()).unwrap(), "/mnt/c/repos2/thing");
+    assert_eq!(translator.to_unix("D:\\other".to_owned()).unwrap(), "/mnt/d/other");
+    assert_eq!(translator.to_win("/home/me/repos2 /mnt/c/x".to_owned()), "/home/me/repos2 c:/x");
+    assert_eq!(translator.to_win("/home/me/other".to_owned()), "/home/me/other");
+    assert_eq!(
+        Translator::default().with_path_map(&path_map).with_separator(WinSeparator::Backslash)
+            .to_win("/srv/data/a/b".to_owned()),
+        "d:\\data\\a\\b");
+}
+
+#[test]
+fn custom_mount_root_translation() {
+    let translator = Translator::new("/windows", HashMap::new(), None);
+    assert_eq!(translator.to_unix("C:\\Users\\test".to_owned()).unwrap(), "/windows/c/Users/test");
+    assert_eq!(translator.to_win("origin  /windows/c/path/ (fetch)".to_owned()), "origin  c:/path/ (fetch)");
+    let root_translator = Translator::new("", HashMap::new(), None);
+    assert_eq!(root_translator.to_unix("C:\\Users\\test".to_owned()).unwrap(), "/c/Users/test");
+    assert_eq!(root_translator.to_win("/c/path /home/a/file".to_owned()), "c:/path /home/a/file");
+}
+
+#[test]
+fn discovered_drive_mounts_translation() {
+    let mut drive_mounts = HashMap::new();
+    drive_mounts.insert("z".to_owned(), "/data".to_owned());
+    let translator = Translator::new(DEFAULT_MOUNT_ROOT, drive_mounts, None);
+    assert_eq!(translator.to_unix("Z:\\repo\\file.txt".to_owned()).unwrap(), "/data/repo/file.txt");
+    assert_eq!(translator.to_unix("C:\\repo".to_owned()).unwrap(), "/mnt/c/repo");
+    assert_eq!(
+        translator.to_win("/data/repo /database/x /mnt/c/repo".to_owned()),
+        "z:/repo /database/x c:/repo");
+}
+
+#[test]
+fn native_path_to_unc_translation() {
+    let translator = Translator::new(DEFAULT_MOUNT_ROOT, HashMap::new(), Some("Ubuntu".to_owned()));
+    assert_eq!(translator.to_win("/home/me/repo".to_owned()), "\\\\wsl$\\Ubuntu\\home\\me\\repo");
+    assert_eq!(
+        translator.to_win(
+            "origin\t/srv/repo.git (fetch)\nmirror\thttps://host/repo /mnt/c/repo".to_owned()),
+        "origin\t\\\\wsl$\\Ubuntu\\srv\\repo.git (fetch)\nmirror\thttps://host/repo c:/repo");
+    let debian_translator = Translator::new(DEFAULT_MOUNT_ROOT, HashMap::new(), Some("Debian".to_owned()));
+    assert_eq!(
+        debian_translator.to_win_path("/home/me/.git/COMMIT_EDITMSG".to_owned()),
+        "\\\\wsl$\\Debian\\home\\me\\.git\\COMMIT_EDITMSG");
+}
+
+#[test]
+fn output_without_paths_fast_path() {
+    let mut drive_mounts = HashMap::new();
+    drive_mounts.insert("z".to_owned(), "/data".to_owned());
+    let translators = vec![
+        Translator::default(),
+        Translator::new("/win", drive_mounts, None),
+        Translator::new(DEFAULT_MOUNT_ROOT, HashMap::new(), Some("Ubuntu".to_owned())),
+        Translator::default().with_path_map(&[("C:\\repos".to_owned(), "/home/me/repos".to_owned())]),
+    ];
+    let lines = [
+        "commit 0123456789abcdef", "    Fix the build", "", "/mnt/c/repo/file.txt",
+        "mnt/c/file", "file:///mnt/c/repo", "/data/file /win/d/x", "/home/me/repos/thing",
+        "M  src/main.rs", "\x1b[33m/mnt/c/colored\x1b[m", "a/b c:/d",
+    ];
+    // the same as running the regex on every line
+    for translator in &translators {
+        for line in lines.iter() {
+            let expected = String::from(translator.wslpath_re.replace_all(
+                line, |caps: &Captures| translator.win_path_replacement(caps)));
+            assert_eq!(translator.to_win(line.to_string()), expected);
+            let expected = String::from(translator.wslpath_re.replace(
+                line, |caps: &Captures| translator.win_path_replacement(caps)));
+            assert_eq!(translator.to_win_path(line.to_string()), expected);
+        }
+    }
+    assert!(!Translator::default().may_contain_linux_paths("    Fix the build /usr/bin"));
+    assert!(Translator::default().may_contain_linux_paths("see /mnt/c/repo"));
+}
+
+#[test]
+fn drive_case_translation() {
+    let lower = Translator::default();
+    assert_eq!(lower.to_unix("C:\\repo".to_owned()).unwrap(), "/mnt/c/repo");
+    assert_eq!(lower.to_unix("d:\\repo".to_owned()).unwrap(), "/mnt/d/repo");
+
+    let upper = Translator::default().with_drive_case(DriveCase::Upper);
+    assert_eq!(upper.to_unix("C:\\repo".to_owned()).unwrap(), "/mnt/C/repo");
+    assert_eq!(upper.to_unix("--git-dir=d:/repo/.git".to_owned()).unwrap(), "--git-dir=/mnt/D/repo/.git");
+    assert_eq!(upper.to_unix("C:\\".to_owned()).unwrap(), "/mnt/C/");
+    assert_eq!(upper.to_win("/mnt/C/repo".to_owned()), "C:/repo");
+
+    let preserve = Translator::default().with_drive_case(DriveCase::Preserve);
+    assert_eq!(preserve.to_unix("C:\\repo".to_owned()).unwrap(), "/mnt/C/repo");
+    assert_eq!(preserve.to_unix("d:\\repo".to_owned()).unwrap(), "/mnt/d/repo");
+
+    // discovered mount points are used as they are
+    let mut drive_mounts = HashMap::new();
+    drive_mounts.insert("z".to_owned(), "/data".to_owned());
+    let translator = Translator::new(DEFAULT_MOUNT_ROOT, drive_mounts, None)
+        .with_drive_case(DriveCase::Upper);
+    assert_eq!(translator.to_unix("Z:\\repo".to_owned()).unwrap(), "/data/repo");
+    assert_eq!(translator.to_unix("z:\\repo".to_owned()).unwrap(), "/data/repo");
+    assert_eq!(Translator::default().to_unix("C:\\repo".to_owned()).unwrap(), "/mnt/c/repo");
+}
+
+#[test]
+fn profile_home_translation() {
+    let translator = Translator::default()
+        .with_path_map(&[("C:\\Users\\me\\repos".to_owned(), "/srv/repos".to_owned())])
+        .with_profile_home(Some(("C:\\Users\\me".to_owned(), "/home/me/".to_owned())));
+    assert_eq!(translator.to_unix("C:\\Users\\me\\.githooks".to_owned()).unwrap(), "/home/me/.githooks");
+    assert_eq!(translator.to_unix("c:/users/ME".to_owned()).unwrap(), "/home/me");
+    assert_eq!(translator.to_unix("--template=C:\\Users\\me\\tpl".to_owned()).unwrap(),
+               "--template=/home/me/tpl");
+    assert_eq!(translator.to_unix_embedded("C:\\Users\\me\\a;D:\\b"), "/home/me/a;/mnt/d/b");
+    // the path map is more specific, other users are not below the profile
+    assert_eq!(translator.to_unix("C:\\Users\\me\\repos\\x".to_owned()).unwrap(), "/srv/repos/x");
+    assert_eq!(translator.to_unix("C:\\Users\\meg\\x".to_owned()).unwrap(), "/mnt/c/Users/meg/x");
+    // not translated back
+    assert_eq!(translator.to_win("/home/me/.githooks /mnt/c/Users/me".to_owned()),
+               "/home/me/.githooks c:/Users/me");
+
+    assert_eq!(Translator::default().with_profile_home(None)
+                   .to_unix("C:\\Users\\me\\.githooks".to_owned()).unwrap(),
+               "/mnt/c/Users/me/.githooks");
+}
+
+#[test]
+fn log_output_translation() {
+    let translator = Translator::default();
+    // `--name-status` and `--stat` print paths relative to the repository
+    for line in &["M\tsrc/main.rs", "A\tmnt/c/notes.md", "R100\told.txt\tdocs/mnt/c/new.txt",
+                  " mnt/c/file.txt | 2 +-", " 1 file changed, 1 insertion(+), 1 deletion(-)"] {
+        assert_eq!(translator.to_win(line.to_string()), *line);
+    }
+    // absolute paths printed by a custom format
+    assert_eq!(translator.to_win("1a2b3c4d /mnt/c/repos/thing".to_owned()), "1a2b3c4d c:/repos/thing");
+    assert_eq!(translator.to_win("worktree: (/mnt/d/wt)".to_owned()), "worktree: (d:/wt)");
+}
+
+#[test]
+fn argument_classification() {
+    let translator = Translator::default();
+    assert_eq!(translator.classify("C:\\repo", false), ArgKind::Drive);
+    assert_eq!(translator.classify("--file=\"D:/a b\"", false), ArgKind::Drive);
+    assert_eq!(translator.classify("C:repo", true), ArgKind::DriveRelative);
+    assert_eq!(translator.classify("C:repo", false), ArgKind::Skipped);
+    assert_eq!(translator.classify("\\\\wsl$\\Ubuntu\\home", false), ArgKind::WslShare);
+    assert_eq!(translator.classify("\\\\server\\share\\x", false), ArgKind::NetworkShare);
+    assert_eq!(translator.classify(".\\src", false), ArgKind::Relative);
+    assert_eq!(translator.classify("src", true), ArgKind::Relative);
+    assert_eq!(translator.classify("src\\main.rs", false), ArgKind::Skipped);
+    assert_eq!(translator.classify("file:///C:/repo", false), ArgKind::FileUrl);
+    assert_eq!(translator.classify("git@host:repo", false), ArgKind::LinuxPathOrUrl);
+    assert_eq!(translator.classify("--pretty=format:%H C:\\a", false), ArgKind::Embedded);
+    assert_eq!(translator.classify("C:\\a;C:\\b", false), ArgKind::Drive);
+    assert_eq!(translator.classify("a;C:\\b", false), ArgKind::Skipped);
+    assert_eq!(Translator::default().with_embedded_paths(true).classify("a;C:\\b", false), ArgKind::Embedded);
+    assert_eq!(translator.classify("HEAD~1", false), ArgKind::Skipped);
+
+    assert_eq!(
+        explain_git_args(&args(&["-C", "repo", "-c", "core.hooksPath=C:\\hooks", "-c", "user.name=me",
+                                 "log", "--", "C:\\f"]), &translator),
+        vec![(GitArgRole::Other, ArgKind::Skipped), (GitArgRole::Path, ArgKind::Relative),
+             (GitArgRole::Other, ArgKind::Skipped), (GitArgRole::Config, ArgKind::Drive),
+             (GitArgRole::Other, ArgKind::Skipped), (GitArgRole::Config, ArgKind::Skipped),
+             (GitArgRole::Other, ArgKind::Skipped), (GitArgRole::Other, ArgKind::Skipped),
+             (GitArgRole::Pathspec, ArgKind::Drive)]);
+    assert_eq!(explain_git_args(&args(&["clone", "--config=core.hooksPath=C:\\h", "https://host/repo", "dest"]),
+                                &translator),
+               vec![(GitArgRole::Other, ArgKind::Skipped), (GitArgRole::ConfigOption, ArgKind::Drive),
+                    (GitArgRole::Other, ArgKind::LinuxPathOrUrl), (GitArgRole::Path, ArgKind::Relative)]);
+}
+
+#[test]
+fn mount_rooted_output_translation() {
+    let translator = Translator::new(DEFAULT_MOUNT_ROOT, HashMap::new(), Some("Ubuntu".to_owned()))
+        .with_path_map(&[("C:\\repos".to_owned(), "/home/me/repos".to_owned())]);
+    // `git stash list`, `git reflog` and `git notes` print messages and relative paths
+    for line in &["stash@{0}: WIP on main: 1a2b3c4 Fix /api/users route",
+                  "stash@{1}: On main: mnt/c/notes.md and ./mnt/c/x",
+                  "1a2b3c4 HEAD@{0}: checkout: moving from main to feature/mnt/c",
+                  "1a2b3c4 HEAD@{1}: commit: Move /home/me/repos/x to src/x",
+                  "0123abcd 4567ef01"] {
+        assert_eq!(translator.to_win_mounted(line.to_string()), *line);
+    }
+    assert_eq!(translator.to_win_mounted("stash@{0}: On main: /mnt/c/repo/untracked.txt".to_owned()),
+               "stash@{0}: On main: c:/repo/untracked.txt");
+    assert_eq!(translator.to_win_mounted("1a2b HEAD@{0}: clone: from /mnt/d/origin.git".to_owned()),
+               "1a2b HEAD@{0}: clone: from d:/origin.git");
+    // unlike the other translated commands
+    assert_eq!(translator.to_win("Fix /api/users route".to_owned()),
+               "Fix \\\\wsl$\\Ubuntu\\api\\users route");
+}
+
+#[test]
+fn pathspec_magic_translation() {
+    let translator = Translator::default();
+    assert_eq!(translator.to_unix(":(exclude)C:\\a\\b".to_owned()).unwrap(), ":(exclude)/mnt/c/a/b");
+    assert_eq!(translator.to_unix(":/C:\\x".to_owned()).unwrap(), ":/:/mnt/c/x");
+    assert_eq!(translator.to_unix(":!D:/tmp/out".to_owned()).unwrap(), ":!:/mnt/d/tmp/out");
+    assert_eq!(translator.to_unix(":^:D:\\out".to_owned()).unwrap(), ":^:/mnt/d/out");
+    assert_eq!(translator.to_unix(":(icase,glob)C:\\Foo\\*.txt".to_owned()).unwrap(),
+               ":(icase,glob)/mnt/c/Foo/*.txt");
+    // the magic is kept with relative paths, which are not translated
+    for arg in &[":(top)subdir", ":/abs", ":!exclude", ":(icase)Foo", ":src\\x", "HEAD:C:\\x"] {
+        assert_eq!(translator.to_unix(arg.to_string()).unwrap(), *arg);
+    }
+    assert_eq!(translator.classify(":(exclude)C:\\a", false), ArgKind::Drive);
+}
+
+#[test]
+fn json_output_translation() {
+    let translator = Translator::default().with_separator(WinSeparator::Backslash);
+    assert_eq!(translator.to_win_json(r#"{"path": "/mnt/c/repo/a.txt", "size": 12}"#.to_owned()),
+               r#"{"path": "c:\\repo\\a.txt", "size": 12}"#);
+    // escaped slashes and quotes, and unchanged literals keep their escapes
+    assert_eq!(translator.to_win_json(r#"["\/mnt\/d\/x", "say \"\/mnt\/c\/y\"", "caf\u00e9"]"#.to_owned()),
+               r#"["d:\\x", "say \"c:\\y\"", "caf\u00e9"]"#);
+    // paths outside of strings and in invalid or unterminated ones stay
+    for line in &[r#"{"n": 1} /mnt/c/x"#, r#"{"bad": "\q /mnt/c/x"}"#, r#"  "text": "/mnt/c/a"#] {
+        assert_eq!(translator.to_win_json(line.to_string()), *line);
+    }
+    assert_eq!(Translator::default().to_win_json(r#"{"dirs": ["/mnt/c/a b", "/mnt/e/b"]}"#.to_owned()),
+               r#"{"dirs": ["c:/a b", "e:/b"]}"#);
+    assert_eq!(unescape_json(r#"\ud83d\ude00 \n"#).as_deref(), Some("\u{1f600} \n"));
+    assert_eq!(unescape_json(r#"\ud83d"#), None);
+    assert_eq!(escape_json("a\"\\\u{1}"), r#"a\"\\\u0001"#);
+}
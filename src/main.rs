@@ -1,223 +1,2094 @@
 #[macro_use]
 extern crate lazy_static;
 extern crate regex;
+extern crate wslgit_for_jetbrains;
 
+/// Print a diagnostic of wslgit itself. Diagnostics always go to stderr,
+/// as stdout carries the (possibly translated) output of git.
+macro_rules! log {
+    ($($arg:tt)*) => {
+        eprintln!("wslgit: {}", format_args!($($arg)*))
+    }
+}
+
+/// Like `log!`, but only if `WSLGIT_DEBUG` is enabled.
+macro_rules! debug {
+    ($($arg:tt)*) => {
+        if *DEBUG {
+            log!($($arg)*)
+        }
+    }
+}
+
+mod config;
+mod credential;
+mod doctor;
+
+use std::collections::HashMap;
 use std::env;
-use std::io::{self, BufRead, BufReader, Write};
-use std::path::{Component, Path, Prefix, PrefixComponent};
-use std::process::{Command, Stdio};
+use std::ffi::OsStr;
+use std::fs::{self, OpenOptions};
+use std::io::{self, BufRead, BufReader, IsTerminal, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitStatus, Stdio};
+use std::thread;
 
 use regex::Regex;
+use wslgit_for_jetbrains::{
+    explain_git_args, find_subcommand, is_absolute_win_path, shell_escape, split_command_line,
+    translate_git_args, translate_git_editor, translate_path_list, translate_ssh_command, DriveCase,
+    Error, RelativePaths, Translator, WinSeparator, DEFAULT_MOUNT_ROOT};
 
-fn get_drive_letter(pc: &PrefixComponent) -> Option<String> {
-    let drive_byte = match pc.kind() {
-        Prefix::VerbatimDisk(d) => Some(d),
-        Prefix::Disk(d) => Some(d),
-        _ => None
+lazy_static! {
+    static ref CONFIG: config::Config = config::load_config();
+    static ref DEBUG: bool = env_flag("WSLGIT_DEBUG");
+    static ref VERBOSE: bool = env_flag("WSLGIT_VERBOSE");
+    static ref WSL_DISTRO: Option<String> = config_var("WSLGIT_DISTRO")
+        .map(|distro| distro.trim().to_owned())
+        .filter(|distro| !distro.is_empty());
+    static ref WSL_USER: Option<String> = config_var("WSLGIT_USER")
+        .map(|user| user.trim().to_owned())
+        .filter(|user| !user.is_empty());
+    /// The setup of the distro git runs in, probed with a single launch of `wsl`.
+    static ref WSL_PROBE: WslProbe = probe_wsl();
+    /// The version of the distro git runs in, if its kernel tells it.
+    static ref WSL_VERSION: Option<WslVersion> = kernel_wsl_version(&WSL_PROBE.os_release);
+    static ref TRANSLATOR: Translator = {
+        let drive_mounts = parse_proc_mounts(&WSL_PROBE.proc_mounts);
+        Translator::new(&get_mount_root(&drive_mounts), drive_mounts, get_unc_distro())
+            .with_separator(get_win_separator())
+            .with_drive_case(get_drive_case())
+            .with_relative_paths(get_relative_paths())
+            .with_embedded_paths(env_flag("WSLGIT_EMBEDDED_PATHS"))
+            .with_env_vars(if env_flag("WSLGIT_EXPAND_VARS") { env::vars().collect() } else { vec![] })
+            .with_path_map(&parse_path_map(&config_var("WSLGIT_PATH_MAP").unwrap_or_default()))
+            .with_profile_home(get_profile_home())
     };
-    drive_byte.map(|drive_letter| {
-        String::from_utf8(vec![drive_letter])
-            .expect(&format!("Invalid drive letter: {}", drive_letter))
-            .to_lowercase()
-    })
 }
 
-fn get_prefix_for_drive(drive: &str) -> String {
-    // todo - lookup mount points
-    format!("/mnt/{}", drive)
+/// Exit code of `wsl.exe` when it fails to start the command, e.g. for
+/// an unknown user. Git itself never exits with it.
+const WSL_FAILURE_EXIT_CODE: i32 = -1;
+
+/// Paths where `wsl.exe` may be: the directories of `path_var`, then the
+/// system directory of `system_root`. A 32-bit process sees the 64-bit
+/// system directory, which contains `wsl.exe`, only as `Sysnative`.
+fn wsl_exe_candidates(path_var: &OsStr, system_root: &Path) -> Vec<PathBuf> {
+    env::split_paths(path_var)
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .map(|dir| dir.join("wsl.exe"))
+        .chain(["System32", "Sysnative"].iter().map(|dir| system_root.join(dir).join("wsl.exe")))
+        .collect()
 }
 
-fn translate_path_to_unix(argument: String) -> String {
-    {
-        let (argname, arg) = if argument.starts_with("--")
-            && argument.contains('=') {
-            let parts: Vec<&str> = argument
-                .splitn(2, '=')
-                .collect();
-            (format!("{}=", parts[0]), parts[1])
-        } else {
-            ("".to_owned(), argument.as_ref())
-        };
-        let win_path = Path::new(arg);
-        if win_path.is_absolute() || win_path.exists() {
-            let wsl_path: String = win_path.components().fold(
-                String::new(), |mut acc, c| {
-                    match c {
-                        Component::Prefix(prefix_comp) => {
-                            let d = get_drive_letter(&prefix_comp).expect(
-                                &format!("Cannot handle path {:?}",
-                                         win_path));
-                            acc.push_str(&get_prefix_for_drive(&d));
-                        }
-                        Component::RootDir => {}
-                        _ => {
-                            let d = c.as_os_str().to_str()
-                                .expect(
-                                    &format!("Cannot represent path {:?}",
-                                             win_path))
-                                .to_owned();
-                            if !acc.is_empty() && !acc.ends_with('/') {
-                                acc.push('/');
-                            }
-                            acc.push_str(&d);
-                        }
-                    };
-                    acc
-                });
-            return format!("{}{}", &argname, &wsl_path);
-        }
-    }
-    argument
-}
-
-fn translate_path_to_win(unix_path: String) -> String {
-    lazy_static! {
-        static ref WSLPATH_RE: Regex =
-            Regex::new(r"(?m-u)/mnt/(?P<drive>[A-Za-z])(?P<path>/\S*)")
-                .expect("Failed to compile WSLPATH regex");
+/// The first of the `candidates` which exists.
+fn find_wsl_exe(candidates: Vec<PathBuf>) -> Option<PathBuf> {
+    candidates.into_iter().find(|candidate| candidate.is_file())
+}
+
+lazy_static! {
+    /// The `wsl.exe` set by the environment variable `WSLGIT_WSL_EXE`, never
+    /// by a configuration file, or else the located one, if any.
+    static ref WSL_EXE: Option<PathBuf> = env::var_os("WSLGIT_WSL_EXE")
+        .filter(|wsl_exe| !wsl_exe.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| find_wsl_exe(wsl_exe_candidates(
+            &env::var_os("PATH").unwrap_or_default(),
+            Path::new(&env::var_os("SystemRoot").unwrap_or_else(|| "C:\\Windows".into())))));
+}
+
+/// A command running `wsl.exe`, reported as a missing `wsl` if it was not found.
+fn wsl() -> Command {
+    Command::new(WSL_EXE.as_deref().unwrap_or_else(|| Path::new("wsl")))
+}
+
+/// Options of `wsl` selecting the `distro` and `user`, followed by the
+/// `extra_args` for the command.
+fn wsl_options(distro: Option<&str>, user: Option<&str>, extra_args: &[String]) -> Vec<String> {
+    let mut options = vec![];
+    if let Some(distro) = distro {
+        options.extend([String::from("-d"), distro.to_owned()]);
+    }
+    if let Some(user) = user {
+        options.extend([String::from("-u"), user.to_owned()]);
     }
-    String::from(WSLPATH_RE.replace(unix_path.as_str(), "${drive}:${path}"))
+    options.extend(extra_args.iter().cloned());
+    options
 }
 
-fn translate_path_to_win_output(line: String) -> String {
-    lazy_static! {
-        static ref WSLPATH_RE: Regex =
-            Regex::new(r"(?m-u)/mnt/(?P<drive>[A-Za-z])(?P<path>/\S*)")
-                .expect("Failed to compile WSLPATH regex");
+/// A `wsl` command running in the distro selected by `WSLGIT_DISTRO`,
+/// as the user selected by `WSLGIT_USER`.
+fn wsl_command() -> Command {
+    let mut wsl = wsl();
+    wsl.args(wsl_options(WSL_DISTRO.as_deref(), WSL_USER.as_deref(), &[]));
+    wsl
+}
+
+/// Split the additional arguments of `wsl` set by `WSLGIT_WSL_ARGS` like a
+/// Windows command line.
+fn parse_wsl_args(wsl_args: &str) -> Result<Vec<String>, String> {
+    if wsl_args.matches('"').count() % 2 == 1 {
+        return Err(String::from("unterminated quote"));
+    }
+    Ok(split_command_line(wsl_args))
+}
+
+lazy_static! {
+    /// The additional arguments of `wsl` for running git.
+    static ref WSL_ARGS: Vec<String> = match parse_wsl_args(
+        &config_var("WSLGIT_WSL_ARGS").unwrap_or_default()) {
+        Ok(wsl_args) => wsl_args,
+        Err(err) => {
+            log!("ignoring WSLGIT_WSL_ARGS: {}", err);
+            vec![]
+        }
+    };
+}
+
+/// Whether to pass the working directory to `wsl --cd`, which older
+/// versions of `wsl.exe` lack. Per default only done for WSL 2 distros, as
+/// `wsl.exe` versions which cannot tell the WSL version lack `--cd` too.
+/// Forced on or off by `WSLGIT_CD`.
+fn uses_wsl_cd() -> bool {
+    match config_var("WSLGIT_CD") {
+        Some(_) => env_flag("WSLGIT_CD"),
+        None => *WSL_VERSION == Some(WslVersion::Wsl2),
+    }
+}
+
+/// The working directory of git: the `cwd_setting` of `WSLGIT_CWD` if it is
+/// set, or else `current_dir`.
+fn git_working_dir(cwd_setting: Option<String>, current_dir: io::Result<PathBuf>)
+                   -> io::Result<PathBuf> {
+    match cwd_setting.as_deref().map(str::trim) {
+        Some(cwd) if !cwd.is_empty() => Ok(PathBuf::from(cwd)),
+        _ => current_dir,
+    }
+}
+
+/// The working directory from `current_dir`, or `None` with a warning if it
+/// cannot be used, e.g. if it was deleted or is not valid Unicode.
+fn working_dir(current_dir: io::Result<PathBuf>) -> Option<String> {
+    match current_dir {
+        Ok(cwd) => match cwd.to_str() {
+            Some(cwd) => Some(cwd.to_owned()),
+            None => {
+                log!("not passing the working directory {} to wsl, it is not valid Unicode",
+                     cwd.display());
+                None
+            }
+        },
+        Err(err) => {
+            log!("cannot get the working directory, git runs in the one WSL chooses: {}", err);
+            None
+        }
+    }
+}
+
+/// Arguments to `wsl` setting its working directory to the translated `cwd`.
+/// Empty when `cwd` has no Linux path and the inherited one must be used.
+fn wsl_cd_args(cwd: Option<&str>, translator: &Translator) -> Vec<String> {
+    match cwd.map(|cwd| translator.to_unix(cwd.to_owned())) {
+        Some(Ok(cwd_unix)) => vec![String::from("--cd"), cwd_unix],
+        Some(Err(err)) => {
+            debug!("not passing the working directory to wsl: {}", err);
+            vec![]
+        }
+        None => vec![],
+    }
+}
+
+/// Decode the output of `wsl.exe` itself, which is UTF-16LE, or UTF-8 with
+/// `WSL_UTF8=1`. UTF-8 text contains no NUL bytes, unlike UTF-16LE.
+fn decode_wsl_output(output: &[u8]) -> String {
+    if !output.starts_with(&[0xff, 0xfe]) && !output.contains(&0) {
+        return String::from_utf8_lossy(output).into_owned();
+    }
+    let wide: Vec<u16> = output.chunks(2)
+        .map(|pair| u16::from(pair[0]) | pair.get(1).map_or(0, |&high| u16::from(high) << 8))
+        .collect();
+    String::from_utf16_lossy(&wide)
+}
+
+/// Distro names from the output of `wsl --list --quiet`.
+fn parse_distro_list(list_output: &str) -> Vec<String> {
+    list_output.lines()
+        .map(|line| line.trim_matches(|c: char| c.is_whitespace() || c == '\u{feff}' || c == '\0'))
+        .filter(|line| !line.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+/// The WSL version a distro runs with.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum WslVersion {
+    Wsl1,
+    Wsl2,
+}
+
+impl WslVersion {
+    fn parse(version: &str) -> Option<WslVersion> {
+        match version {
+            "1" => Some(WslVersion::Wsl1),
+            "2" => Some(WslVersion::Wsl2),
+            _ => None,
+        }
+    }
+}
+
+/// A distro as listed by `wsl --list --verbose`.
+#[derive(Debug, PartialEq)]
+struct DistroInfo {
+    name: String,
+    version: Option<WslVersion>,
+    is_default: bool,
+}
+
+/// Parse the output of `wsl --list --verbose`, which marks the default
+/// distro with a `*` before the `NAME  STATE  VERSION` columns.
+fn parse_distro_list_verbose(list_output: &str) -> Vec<DistroInfo> {
+    parse_distro_list(list_output).iter()
+        .filter_map(|line| {
+            let (is_default, line) = match line.strip_prefix('*') {
+                Some(rest) => (true, rest),
+                None => (false, line.as_str()),
+            };
+            match line.split_whitespace().collect::<Vec<_>>().as_slice() {
+                ["NAME", ..] => None,
+                [name, .., version] => Some(DistroInfo {
+                    name: name.to_string(),
+                    version: WslVersion::parse(version),
+                    is_default,
+                }),
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+/// The registered distros, empty if `wsl` cannot list them verbosely.
+fn registered_distros() -> Vec<DistroInfo> {
+    wsl()
+        .args(["--list", "--verbose"])
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .map(|output| parse_distro_list_verbose(&decode_wsl_output(&output.stdout)))
+        .unwrap_or_default()
+}
+
+/// The distro `wsl_command` runs in: the one named `selected`, or else the
+/// default one.
+fn find_distro<'a>(distros: &'a [DistroInfo], selected: Option<&str>) -> Option<&'a DistroInfo> {
+    match selected {
+        Some(selected) => distros.iter().find(|distro| distro.name.eq_ignore_ascii_case(selected)),
+        None => distros.iter().find(|distro| distro.is_default),
+    }
+}
+
+/// Whether `wsl --list --quiet` lists `distro`, checked after `wsl` failed to
+/// start git. True if `wsl` cannot list the distros.
+fn is_distro_registered(distro: &str) -> bool {
+    wsl()
+        .args(["--list", "--quiet"])
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .map(|output| parse_distro_list(&decode_wsl_output(&output.stdout))
+            .iter()
+            .any(|registered| registered.eq_ignore_ascii_case(distro)))
+        .unwrap_or(true)
+}
+
+/// Whether `wsl --list --quiet` shows that no distro is installed: it lists
+/// none, or fails with a message instead of the list.
+fn lists_no_distros(success: bool, list_output: &str) -> bool {
+    !success || parse_distro_list(list_output).is_empty()
+}
+
+/// Whether `wsl` has no default distro, checked after it failed to start git.
+fn is_default_distro_missing() -> bool {
+    wsl()
+        .args(["--list", "--quiet"])
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .map(|output| lists_no_distros(
+            output.status.success(), &decode_wsl_output(&output.stdout)))
+        .unwrap_or(false)
+}
+
+/// The setting `name`, from the environment variable of that name or else
+/// from the `.wslgit` file or the global configuration file.
+fn config_var(name: &str) -> Option<String> {
+    config::setting(name, env::var(name).ok(), &CONFIG)
+}
+
+/// Whether the setting `name` is set to a truthy value.
+fn env_flag(name: &str) -> bool {
+    match config_var(name) {
+        Some(value) => {
+            let value = value.trim().to_lowercase();
+            !(value.is_empty() || value == "0" || value == "false" || value == "no")
+        }
+        None => false,
+    }
+}
+
+fn normalize_mount_root(root: &str) -> String {
+    root.trim().trim_end_matches('/').to_owned()
+}
+
+/// Extracts `root` from the `[automount]` section of a `wsl.conf` file.
+fn parse_automount_root(wsl_conf: &str) -> Option<String> {
+    let mut in_automount = false;
+    for line in wsl_conf.lines() {
+        let line = line.split('#').next().unwrap_or_default().trim();
+        if line.starts_with('[') && line.ends_with(']') {
+            in_automount = line[1..line.len() - 1].trim().eq_ignore_ascii_case("automount");
+        } else if in_automount {
+            let mut parts = line.splitn(2, '=');
+            let key = parts.next().unwrap_or_default().trim();
+            if let (true, Some(value)) = (key == "root", parts.next()) {
+                let value = value.trim().trim_matches('"');
+                if !value.is_empty() {
+                    return Some(normalize_mount_root(value));
+                }
+            }
+        }
+    }
+    None
+}
+
+/// The directory most of the `drive_mounts` are in, named by their drive
+/// letter like `/mnt/c`, which is where WSL mounts the drives.
+fn mount_root_of(drive_mounts: &HashMap<String, String>) -> Option<String> {
+    let mut roots: Vec<&str> = drive_mounts.iter()
+        .filter_map(|(drive, mount_point)| {
+            let (root, name) = mount_point.rsplit_once('/')?;
+            if name.eq_ignore_ascii_case(drive) { Some(root) } else { None }
+        })
+        .collect();
+    roots.sort_unstable();
+    let count = |root: &str| roots.iter().filter(|&&other| other == root).count();
+    // the first of the most frequent roots, for a stable choice
+    let root = roots.iter().rev().max_by_key(|&&root| count(root))?;
+    Some(normalize_mount_root(root))
+}
+
+/// Determine the directory under which WSL mounts the Windows drives: from
+/// `WSLGIT_MOUNT_ROOT`, or from the discovered `drive_mounts`, or else from
+/// `/etc/wsl.conf` inside WSL.
+fn get_mount_root(drive_mounts: &HashMap<String, String>) -> String {
+    if let Some(root) = config_var("WSLGIT_MOUNT_ROOT") {
+        if !root.trim().is_empty() {
+            return normalize_mount_root(&root);
+        }
     }
-    String::from(WSLPATH_RE.replace_all(line.as_str(), "${drive}:${path}"))
+    mount_root_of(drive_mounts)
+        .or_else(|| parse_automount_root(&WSL_PROBE.wsl_conf))
+        .unwrap_or_else(|| DEFAULT_MOUNT_ROOT.to_owned())
 }
 
-fn is_translated_command(arg: String) -> bool {
-    const TRANSLATED_CMDS: &[&str] = &["rev-parse", "remote"];
-    TRANSLATED_CMDS.contains(&arg.as_str())
+/// Decode the octal escapes (e.g. `\040` for a space) used in `/proc/mounts`.
+fn unescape_mount_field(field: &str) -> String {
+    let mut result = String::with_capacity(field.len());
+    let mut rest = field;
+    while let Some(pos) = rest.find('\\') {
+        result.push_str(&rest[..pos]);
+        let escaped = rest.get(pos + 1..pos + 4)
+            .and_then(|octal| u8::from_str_radix(octal, 8).ok());
+        match escaped {
+            Some(byte) => {
+                result.push(byte as char);
+                rest = &rest[pos + 4..];
+            }
+            None => {
+                result.push('\\');
+                rest = &rest[pos + 1..];
+            }
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+fn normalize_mount_point(mount_point: &str) -> String {
+    match mount_point.trim_end_matches('/') {
+        "" => "/".to_owned(),
+        trimmed => trimmed.to_owned(),
+    }
+}
+
+/// Build a map from lowercase drive letter to mount point from the
+/// drvfs entries of `/proc/mounts`.
+fn parse_proc_mounts(proc_mounts: &str) -> HashMap<String, String> {
+    let mut drive_mounts = HashMap::new();
+    for line in proc_mounts.lines() {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.len() < 3 || (fields[2] != "drvfs" && fields[2] != "9p") {
+            continue;
+        }
+        let device = unescape_mount_field(fields[0]);
+        // WSL2 reports the drive in the `path=` option of 9p mounts
+        let options = fields.get(3).cloned().unwrap_or_default();
+        let source = options.split([',', ';'])
+            .find(|option| option.starts_with("path="))
+            .map(|option| unescape_mount_field(&option[5..]))
+            .unwrap_or(device);
+        let source = source.as_bytes();
+        if source.len() >= 2 && source[0].is_ascii_alphabetic() && source[1] == b':' {
+            drive_mounts.insert(
+                (source[0] as char).to_ascii_lowercase().to_string(),
+                normalize_mount_point(&unescape_mount_field(fields[1])));
+        }
+    }
+    drive_mounts
 }
 
-fn is_version_command(arg: String) -> bool {
+/// Prints the files and variables `WslProbe` holds, separated by NUL bytes.
+const PROBE_SCRIPT: &str = "cat /proc/sys/kernel/osrelease; printf '\\000'; cat /proc/mounts; \
+                            printf '\\000'; cat /etc/wsl.conf 2>/dev/null; \
+                            printf '\\000%s\\000%s' \"$HOME\" \"$WSL_DISTRO_NAME\"";
+
+/// What wslgit needs to know about the distro git runs in. It is probed
+/// with a single launch of `wsl`, as every launch adds to the latency.
+#[derive(Debug, Default, PartialEq)]
+struct WslProbe {
+    /// `/proc/sys/kernel/osrelease`
+    os_release: String,
+    /// `/proc/mounts`
+    proc_mounts: String,
+    /// `/etc/wsl.conf`, empty if there is none
+    wsl_conf: String,
+    home: String,
+    /// `WSL_DISTRO_NAME`
+    distro_name: String,
+}
+
+/// Parse the output of `PROBE_SCRIPT`, missing parts are empty.
+fn parse_probe(output: &str) -> WslProbe {
+    let mut parts = output.split('\0').map(String::from);
+    WslProbe {
+        os_release: parts.next().unwrap_or_default().trim().to_owned(),
+        proc_mounts: parts.next().unwrap_or_default(),
+        wsl_conf: parts.next().unwrap_or_default(),
+        home: parts.next().unwrap_or_default(),
+        distro_name: parts.next().unwrap_or_default().trim().to_owned(),
+    }
+}
+
+/// Probe the distro git runs in, empty if `wsl` fails.
+fn probe_wsl() -> WslProbe {
+    wsl_command()
+        .args(["-e", "sh", "-c", PROBE_SCRIPT])
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .ok()
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+        .map(|output| parse_probe(&output))
+        .unwrap_or_default()
+}
+
+/// The WSL version of a distro by its kernel release, e.g.
+/// `5.15.90.1-microsoft-standard-WSL2` or `4.4.0-19041-Microsoft` for WSL 1.
+fn kernel_wsl_version(os_release: &str) -> Option<WslVersion> {
+    if os_release.contains("microsoft-standard") || os_release.contains("WSL2") {
+        Some(WslVersion::Wsl2)
+    } else if os_release.contains("Microsoft") {
+        Some(WslVersion::Wsl1)
+    } else {
+        None
+    }
+}
+
+/// The Windows user profile and the home directory of the user inside WSL,
+/// only determined if `WSLGIT_MAP_HOME` maps the one to the other.
+fn get_profile_home() -> Option<(String, String)> {
+    if !env_flag("WSLGIT_MAP_HOME") {
+        return None;
+    }
+    let profile = env::var("USERPROFILE").ok().filter(|profile| is_absolute_win_path(profile))?;
+    let home = Some(WSL_PROBE.home.trim().to_owned()).filter(|home| home.starts_with('/'));
+    if home.is_none() {
+        log!("failed to find the home directory inside WSL for WSLGIT_MAP_HOME");
+    }
+    home.map(|home| (profile, home))
+}
+
+/// The distro used for `\\\\wsl$\\<distro>` paths in the output, only
+/// determined if `WSLGIT_UNC_OUTPUT` is enabled.
+fn get_unc_distro() -> Option<String> {
+    if !env_flag("WSLGIT_UNC_OUTPUT") {
+        return None;
+    }
+    if let Some(ref distro) = *WSL_DISTRO {
+        return Some(distro.clone());
+    }
+    Some(WSL_PROBE.distro_name.clone()).filter(|distro| !distro.is_empty())
+}
+
+/// How the git command is started inside WSL.
+#[derive(Debug, PartialEq)]
+enum ShellMode {
+    /// `wsl git ...`, run by the default shell of the WSL user.
+    Direct,
+    /// `wsl -e bash -lc '...'`, sourcing the login profile for PATH and aliases.
+    Login,
+    /// `wsl -e bash -lic '...'`, sourcing `~/.bashrc` as well.
+    Interactive,
+}
+
+/// Select the shell mode from `WSLGIT_SHELL_MODE` (`direct`, `login` or
+/// `interactive`). `WSLGIT_LOGIN_SHELL` is a shorthand for `interactive`.
+fn get_shell_mode() -> ShellMode {
+    match config_var("WSLGIT_SHELL_MODE") {
+        Some(ref mode) if mode.trim().eq_ignore_ascii_case("login") => ShellMode::Login,
+        Some(ref mode) if mode.trim().eq_ignore_ascii_case("interactive") => ShellMode::Interactive,
+        None if env_flag("WSLGIT_LOGIN_SHELL") => ShellMode::Interactive,
+        _ => ShellMode::Direct,
+    }
+}
+
+lazy_static! {
+    /// The lines printed by the shell profiles, like a banner, which are
+    /// dropped from the errors of git in the shell modes.
+    static ref STDERR_FILTER: Option<Regex> = config_var("WSLGIT_STDERR_FILTER")
+        .filter(|pattern| !pattern.is_empty())
+        .and_then(|pattern| match Regex::new(&pattern) {
+            Ok(filter) => Some(filter),
+            Err(err) => {
+                log!("ignoring WSLGIT_STDERR_FILTER: {}", err);
+                None
+            }
+        });
+}
+
+/// Whether `line` is one of the warnings of an interactive bash without a
+/// terminal, or matches the `filter` of `WSLGIT_STDERR_FILTER`. These are
+/// not from git and dropped from its errors.
+fn is_shell_noise(line: &str, filter: Option<&Regex>) -> bool {
+    line.starts_with("bash: cannot set terminal process group")
+        || line == "bash: no job control in this shell"
+        || filter.is_some_and(|filter| filter.is_match(line))
+}
+
+/// Arguments to `wsl` running the shell escaped `git_args`.
+fn wsl_command_args(shell_mode: &ShellMode, git_args: Vec<String>) -> Vec<String> {
+    match *shell_mode {
+        ShellMode::Direct => git_args,
+        ShellMode::Login => vec![
+            String::from("-e"), String::from("bash"), String::from("-lc"), git_args.join(" ")],
+        ShellMode::Interactive => vec![
+            String::from("-e"), String::from("bash"), String::from("-lic"), git_args.join(" ")],
+    }
+}
+
+/// Longest command line Windows can start a program with, in UTF-16 units.
+const MAX_COMMAND_LINE: usize = 32767;
+
+/// Whether the command line of `wsl` with `wsl_args` may exceed the length
+/// Windows allows, counting a separating space and quotes for each argument.
+fn exceeds_command_line_limit(wsl_args: &[String]) -> bool {
+    let len = "wsl".len() + wsl_args.iter()
+        .map(|arg| arg.encode_utf16().count() + 3)
+        .sum::<usize>();
+    len > MAX_COMMAND_LINE
+}
+
+/// Arguments to `wsl` running the shell escaped git command line stored in
+/// the file `script_path`, for command lines too long to pass directly.
+fn wsl_script_args(shell_mode: &ShellMode, script_path: &str) -> Vec<String> {
+    let shell: &[&str] = match *shell_mode {
+        ShellMode::Direct => &["sh"],
+        ShellMode::Login => &["bash", "-l"],
+        ShellMode::Interactive => &["bash", "-li"],
+    };
+    let mut args = vec![String::from("-e")];
+    args.extend(shell.iter().map(|arg| arg.to_string()));
+    args.push(script_path.to_owned());
+    args
+}
+
+/// A temporary file, removed when it goes out of scope.
+struct TempFile(PathBuf);
+
+impl TempFile {
+    /// Create a new file with `contents` in `dir`, named after `prefix`, the
+    /// process id and a counter. Files already existing are never opened, so
+    /// other users of a shared temporary directory cannot substitute them.
+    fn create(dir: &Path, prefix: &str, contents: &str) -> io::Result<TempFile> {
+        let nanos = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |time| time.subsec_nanos());
+        for attempt in 0..100 {
+            let path = dir.join(format!("{}-{}-{:08x}-{}.sh", prefix, std::process::id(), nanos, attempt));
+            let mut file = match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(file) => file,
+                Err(ref err) if err.kind() == io::ErrorKind::AlreadyExists => continue,
+                Err(err) => return Err(err),
+            };
+            let temp_file = TempFile(path);
+            file.write_all(contents.as_bytes())?;
+            return Ok(temp_file);
+        }
+        Err(io::Error::new(io::ErrorKind::AlreadyExists, "no unused temporary file name"))
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.0);
+    }
+}
+
+/// Git commands whose output is translated to Windows paths by default.
+const TRANSLATED_CMDS: &[&str] = &[
+    "rev-parse", "remote", "check-ignore", "check-attr", "ls-files", "reflog"];
+
+/// Translated commands whose output rarely contains paths, but messages and
+/// relative paths. Only the paths below the drive mounts are translated.
+const MOUNT_ROOTED_CMDS: &[&str] = &["notes", "stash", "reflog"];
+
+lazy_static! {
+    static ref TRANSLATED_COMMANDS: Vec<String> = merge_config_list(
+        TRANSLATED_CMDS, &config_var("WSLGIT_TRANSLATE_CMDS").unwrap_or_default());
+}
+
+/// Merge the comma-separated `config` into the `defaults`, an entry
+/// prefixed with `-` is removed instead.
+fn merge_config_list(defaults: &[&str], config: &str) -> Vec<String> {
+    let mut commands: Vec<String> = defaults.iter().map(|cmd| cmd.to_string()).collect();
+    for cmd in config.split(',').map(str::trim).filter(|cmd| !cmd.is_empty()) {
+        if let Some(removed) = cmd.strip_prefix('-') {
+            commands.retain(|existing| existing != removed);
+        } else if !commands.iter().any(|existing| existing == cmd) {
+            commands.push(cmd.to_owned());
+        }
+    }
+    commands
+}
+
+/// Editor variables run on Windows through `wslgit win-cmd`.
+const EDITOR_VARS: &[&str] = &["GIT_EDITOR", "GIT_SEQUENCE_EDITOR"];
+
+/// Variables of git naming a file or directory, which are translated even if
+/// they are relative paths.
+const GIT_PATH_VARS: &[&str] = &[
+    "GIT_DIR", "GIT_WORK_TREE", "GIT_OBJECT_DIRECTORY", "GIT_CONFIG_GLOBAL", "GIT_CONFIG_SYSTEM"];
+
+/// Windows variables forwarded into WSL by default, besides the `GIT_*` ones.
+/// Like `GIT_ASKPASS`, the credential helper of ssh is a Windows program run
+/// through the WSL interop, so only its path needs to be translated.
+const FORWARDED_VARS: &[&str] = &[
+    "http_proxy", "https_proxy", "no_proxy", "HTTP_PROXY", "HTTPS_PROXY", "NO_PROXY",
+    "SSH_ASKPASS", "SSH_ASKPASS_REQUIRE"];
+
+lazy_static! {
+    static ref FORWARDED_VARIABLES: Vec<String> = merge_config_list(
+        FORWARDED_VARS, &config_var("WSLGIT_FORWARD_ENV").unwrap_or_default());
+}
+
+/// The `GIT_*` and `forwarded` variables of `vars` to set for git inside
+/// WSL, with the editors and path values translated, and `WSLENV`
+/// forwarding them into WSL in addition to the ones it already lists.
+fn wsl_git_env<I>(wslgit_cmd: &str, forwarded: &[String], translator: &Translator,
+                  vars: I) -> Vec<(String, String)>
+    where I: IntoIterator<Item=(String, String)> {
+    let mut git_env = vec![];
+    let mut wsl_env: Vec<String> = vec![];
+    let mut inherited_wsl_env = String::new();
+    for (env_key, env_val) in vars {
+        if env_key == "WSLENV" {
+            inherited_wsl_env = env_val;
+        } else if env_key.starts_with("GIT_") || forwarded.contains(&env_key) {
+            wsl_env.push(format!("{}/u", env_key));
+            if EDITOR_VARS.contains(&env_key.as_str()) {
+                let editor = translate_git_editor(wslgit_cmd, &env_val);
+                git_env.push((env_key, editor));
+            } else if env_key == "GIT_SSH_COMMAND" {
+                let ssh_command = translate_ssh_command(&env_val, translator);
+                git_env.push((env_key, ssh_command));
+            } else if GIT_PATH_VARS.contains(&env_key.as_str()) && !env_val.is_empty() {
+                let path = translator.to_unix_path(env_val.clone()).unwrap_or(env_val);
+                git_env.push((env_key, path));
+            } else if let Some(paths) = translate_path_list(&env_val, translator) {
+                git_env.push((env_key, paths));
+            } else if is_absolute_win_path(&env_val) {
+                let path = translator.to_unix(env_val.clone()).unwrap_or(env_val);
+                git_env.push((env_key, path));
+            } else {
+                git_env.push((env_key, env_val));
+            }
+        }
+    }
+    if !wsl_env.is_empty() {
+        let forwarded_keys: Vec<&str> = git_env.iter().map(|(key, _)| key.as_str()).collect();
+        let mut entries: Vec<String> = inherited_wsl_env.split(':')
+            .filter(|entry| !entry.is_empty())
+            .filter(|entry| !forwarded_keys.contains(&entry.split('/').next().unwrap_or_default()))
+            .map(String::from)
+            .collect();
+        entries.extend(wsl_env);
+        git_env.push((String::from("WSLENV"), entries.join(":")));
+    }
+    git_env
+}
+
+fn is_translated_command(arg: &str) -> bool {
+    TRANSLATED_COMMANDS.iter().any(|cmd| cmd == arg)
+}
+
+fn is_version_command(arg: &str) -> bool {
     const MATCHES: &[&str] = &["version", "--version"];
-    MATCHES.contains(&arg.as_str())
+    MATCHES.contains(&arg)
+}
+
+/// Git commands whose output is translated if one of the first options is
+/// given and none of the second, e.g. the machine readable formats of
+/// `git status` parsed by IDEs. The custom formats of `git log` may print
+/// absolute paths, but not its patches, which show the contents of files.
+/// Capturing the output also takes the console from the editor of
+/// `config --edit` and the progress of `submodule update` or `worktree add`.
+const TRANSLATED_CMD_FORMATS: &[(&str, &[&str], &[&str])] = &[
+    ("status", &["--porcelain", "--short", "-s", "-z"], &[]),
+    ("log", &["--format", "--pretty"], &["-p", "-u", "--patch"]),
+    // not the interactive `stash push -p` or the editor of `notes edit`
+    ("stash", &["list"], &[]),
+    ("notes", &["list"], &[]),
+    ("config", &["--get", "--get-all", "--get-regexp", "--get-urlmatch", "--list", "-l",
+                 "--show-origin", "get", "list"], &["--edit", "-e", "edit"]),
+    ("submodule", &["status"], &[]),
+    ("worktree", &["list"], &[]),
+];
+
+/// Whether `args` of the subcommand `cmd` select a translated output format.
+fn is_translated_format(cmd: &str, args: &[String]) -> bool {
+    let (opts, excluded_opts) = match TRANSLATED_CMD_FORMATS.iter().find(|&&(name, ..)| name == cmd) {
+        Some(&(_, opts, excluded_opts)) => (opts, excluded_opts),
+        None => return false,
+    };
+    let has_option = |opts: &[&str]| args.iter()
+        .take_while(|arg| *arg != "--")
+        .any(|arg| opts.iter().any(|opt| {
+            arg == opt || opt.starts_with("--") && arg.starts_with(&format!("{}=", opt))
+        }));
+    has_option(opts) && !has_option(excluded_opts)
 }
 
-fn arg_matching(f: fn(String) -> bool) -> bool {
-    env::args().skip(1).position(f).is_some()
+/// Whether only the paths below the drive mounts are translated in the
+/// output of the git subcommand of `args`.
+fn is_mount_rooted_invocation(args: &[String]) -> bool {
+    find_subcommand(args).is_some_and(|pos| MOUNT_ROOTED_CMDS.contains(&args[pos].as_str()))
+}
+
+/// Whether the git subcommand of `args` prints paths to be translated.
+fn is_translated_invocation(args: &[String]) -> bool {
+    find_subcommand(args).is_some_and(|pos| {
+        is_translated_command(&args[pos]) || is_translated_format(&args[pos], &args[pos + 1..])
+    })
+}
+
+/// Whether `args` ask for the version, as subcommand or global option.
+fn is_version_invocation(args: &[String]) -> bool {
+    let end = find_subcommand(args).map_or(args.len(), |pos| pos + 1);
+    args[..end].iter().any(|arg| is_version_command(arg))
 }
 
 fn append_version(line: String) -> String {
     line + " wslgit-for-jetbrains." + env!("CARGO_PKG_VERSION")
 }
 
-fn main() {
+/// The exit code forwarding `status`, using the shell convention of
+/// 128 + signal number for children terminated by a signal.
+fn forwarded_exit_code(status: ExitStatus) -> i32 {
+    if let Some(exit_code) = status.code() {
+        return exit_code;
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::ExitStatusExt;
+        if let Some(signal) = status.signal() {
+            return 128 + signal;
+        }
+    }
+    1
+}
+
+/// Stream the lines of `input` to `output`, transformed by
+/// `transform_output`. Lines which are not valid UTF-8 are passed through
+/// unchanged, as are the line endings.
+fn forward_output<R: BufRead, W: Write>(input: R, output: &mut W,
+                                        transform_output: fn(String) -> String)
+                                        -> io::Result<()> {
+    forward_lines(input, output, transform_output, |_| true)
+}
+
+/// Longest part of a line without a line break which is buffered. Longer
+/// lines are transformed in parts, split after whitespace, which ends any
+/// path, so no path is split.
+const MAX_LINE_PART: usize = 64 * 1024;
+
+/// Longest part of a line without any whitespace which is buffered.
+const MAX_WORD_PART: usize = 4 * MAX_LINE_PART;
+
+/// Forward the lines of `input` like `forward_output`, except the valid
+/// UTF-8 lines not accepted by `keeps_line`.
+fn forward_lines<R: BufRead, W: Write>(mut input: R, output: &mut W,
+                                       transform_output: fn(String) -> String,
+                                       keeps_line: fn(&str) -> bool)
+                                       -> io::Result<()> {
+    let mut line = vec![];
+    // whether parts of the line were written already
+    let mut is_continued = false;
+    // after the last whitespace in `line`
+    let mut word_start = 0;
+    loop {
+        let line_len = line.len();
+        let (read, has_newline) = {
+            let available = input.fill_buf()?;
+            match available.iter().position(|&b| b == b'\n') {
+                Some(pos) => {
+                    line.extend_from_slice(&available[..pos]);
+                    (pos + 1, true)
+                }
+                None => {
+                    line.extend_from_slice(available);
+                    (available.len(), false)
+                }
+            }
+        };
+        input.consume(read);
+        if let Some(pos) = line[line_len..].iter()
+            .rposition(|b| b.is_ascii_whitespace() || b.is_ascii_control()) {
+            word_start = line_len + pos + 1;
+        }
+        if read == 0 || has_newline {
+            let keeps_line = if is_continued { |_: &str| true } else { keeps_line };
+            let is_written = (read == 0 && line.is_empty())
+                || forward_line_part(std::mem::take(&mut line), output, transform_output, keeps_line)?;
+            if has_newline && is_written {
+                output.write_all(b"\n")?;
+            }
+            if read == 0 {
+                break;
+            }
+            is_continued = false;
+            word_start = 0;
+        } else if line.len() >= MAX_LINE_PART && (word_start > 0 || line.len() >= MAX_WORD_PART) {
+            let split = if word_start > 0 { word_start } else { line.len() };
+            let rest = line.split_off(split);
+            forward_line_part(std::mem::replace(&mut line, rest), output, transform_output, |_| true)?;
+            output.flush()?;
+            is_continued = true;
+            word_start = 0;
+        }
+    }
+    output.flush()
+}
+
+/// Write the `part` of a line transformed by `transform_output`, or
+/// unchanged if it is not valid UTF-8. Returns false if `keeps_line` drops it.
+fn forward_line_part<W: Write>(part: Vec<u8>, output: &mut W,
+                               transform_output: fn(String) -> String,
+                               keeps_line: fn(&str) -> bool) -> io::Result<bool> {
+    match String::from_utf8(part) {
+        Ok(ref text) if !keeps_line(text) => return Ok(false),
+        Ok(text) => output.write_all(transform_output(text).as_bytes())?,
+        Err(err) => output.write_all(err.as_bytes())?,
+    }
+    Ok(true)
+}
+
+/// Forward `input` to `output` like `forward_lines`, in a separate thread.
+fn forward_in_background<R, W>(input: R, mut output: W, transform_output: fn(String) -> String,
+                               keeps_line: fn(&str) -> bool)
+                               -> thread::JoinHandle<io::Result<W>>
+    where R: Read + Send + 'static, W: Write + Send + 'static {
+    thread::spawn(move || {
+        forward_lines(BufReader::new(input), &mut output, transform_output, keeps_line)?;
+        Ok(output)
+    })
+}
+
+/// Whether the errors of translated commands are translated as well,
+/// which can be disabled with `WSLGIT_TRANSLATE_STDERR`.
+fn translates_stderr() -> bool {
+    config_var("WSLGIT_TRANSLATE_STDERR").is_none() || env_flag("WSLGIT_TRANSLATE_STDERR")
+}
+
+/// When to keep the colors of captured output, from `WSLGIT_COLOR`.
+#[derive(Debug, PartialEq)]
+enum ColorMode {
+    /// Only if wslgit writes to a console (the default).
+    Auto,
+    Always,
+    Never,
+}
+
+/// Select the separator of translated Windows paths from `WSLGIT_WIN_SEP`
+/// (`slash` or `backslash`).
+fn get_win_separator() -> WinSeparator {
+    match config_var("WSLGIT_WIN_SEP") {
+        Some(ref separator) if separator.trim().eq_ignore_ascii_case("backslash") =>
+            WinSeparator::Backslash,
+        _ => WinSeparator::Slash,
+    }
+}
+
+/// Select the case of the drive letters below the mount root from
+/// `WSLGIT_DRIVE_CASE` (`lower`, `upper` or `preserve`).
+fn get_drive_case() -> DriveCase {
+    match config_var("WSLGIT_DRIVE_CASE").unwrap_or_default().trim().to_lowercase().as_str() {
+        "upper" => DriveCase::Upper,
+        "preserve" => DriveCase::Preserve,
+        _ => DriveCase::Lower,
+    }
+}
+
+/// Parse the comma-separated `WIN_DIR=LINUX_DIR` entries of `WSLGIT_PATH_MAP`,
+/// ignoring invalid ones with a warning.
+fn parse_path_map(path_map: &str) -> Vec<(String, String)> {
+    path_map.split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .filter_map(|entry| match entry.split_once('=') {
+            Some((win_dir, unix_dir)) if is_absolute_win_path(win_dir.trim())
+                && unix_dir.trim().starts_with('/') =>
+                Some((win_dir.trim().to_owned(), unix_dir.trim().to_owned())),
+            _ => {
+                log!("ignoring '{}' in WSLGIT_PATH_MAP, expected 'C:\\dir=/linux/dir'", entry);
+                None
+            }
+        })
+        .collect()
+}
+
+/// Select the relative arguments translated as paths from
+/// `WSLGIT_RELATIVE_PATHS` (`explicit` or `existing`).
+fn get_relative_paths() -> RelativePaths {
+    match config_var("WSLGIT_RELATIVE_PATHS") {
+        Some(ref relative_paths) if relative_paths.trim().eq_ignore_ascii_case("existing") =>
+            RelativePaths::Existing,
+        _ => RelativePaths::Explicit,
+    }
+}
+
+fn get_color_mode() -> ColorMode {
+    match config_var("WSLGIT_COLOR").unwrap_or_default().trim().to_lowercase().as_str() {
+        "always" => ColorMode::Always,
+        "never" => ColorMode::Never,
+        _ => ColorMode::Auto,
+    }
+}
+
+/// Global git options for commands whose output is captured by wslgit
+/// instead of going to the console directly.
+fn captured_output_options(captures_output: bool, color_mode: &ColorMode,
+                           is_console: bool) -> Vec<String> {
+    if !captures_output {
+        return vec![];
+    }
+    // the pager would wait for the console that git does not see
+    let mut options = vec![String::from("--no-pager")];
+    // git only colors its output for a console, not the pipe to wslgit
+    if *color_mode == ColorMode::Always || (*color_mode == ColorMode::Auto && is_console) {
+        options.extend(vec![String::from("-c"), String::from("color.ui=always")]);
+    }
+    options
+}
+
+/// Whether git gets the stdin of wslgit, e.g. for commit messages. It is
+/// closed for `--version`, as git may wait for input when started by IDEs
+/// to check if it works.
+fn needs_stdin(git_cli_args: &[String]) -> bool {
+    !is_version_invocation(git_cli_args)
+}
+
+/// How wslgit runs the given command.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum ExecutionMode {
+    /// Run a Windows command for git inside WSL, e.g. the editor.
+    WinCmd,
+    /// Run git inside WSL, translating the paths.
+    Wsl,
+    /// Already running inside WSL, run the Linux git without translation.
+    Native,
+    /// Print the `wslgit doctor` report instead of running git.
+    Doctor,
+    /// Configure the Windows credential manager for git inside WSL.
+    InstallCredentialHelper,
+}
+
+fn execution_mode(first_arg: Option<&str>, inside_wsl: bool) -> ExecutionMode {
+    if inside_wsl {
+        ExecutionMode::Native
+    } else if first_arg == Some("win-cmd") {
+        ExecutionMode::WinCmd
+    } else if first_arg == Some("doctor") {
+        ExecutionMode::Doctor
+    } else if first_arg == Some("install-credential-helper") {
+        ExecutionMode::InstallCredentialHelper
+    } else {
+        ExecutionMode::Wsl
+    }
+}
+
+/// Whether this is a Linux build of wslgit running inside WSL itself.
+fn is_inside_wsl() -> bool {
+    cfg!(target_os = "linux") && (env::var_os("WSL_DISTRO_NAME").is_some()
+        || fs::read_to_string("/proc/version")
+            .map(|version| version.to_lowercase().contains("microsoft"))
+            .unwrap_or(false))
+}
+
+/// The first `git` on `path_var` which is not wslgit itself, so wslgit
+/// installed as `git` inside WSL does not start itself again.
+fn find_native_git(path_var: &OsStr, self_exe: Option<&Path>) -> PathBuf {
+    let self_exe = self_exe.and_then(|exe| exe.canonicalize().ok());
+    env::split_paths(path_var)
+        .map(|dir| dir.join("git"))
+        .filter(|git| git.is_file())
+        .find(|git| git.canonicalize().ok() != self_exe)
+        .unwrap_or_else(|| PathBuf::from("/usr/bin/git"))
+}
+
+/// The options of wslgit itself, set by flags git never sees.
+#[derive(Debug, Default, PartialEq)]
+struct WslgitOptions {
+    /// `--wslgit-dry-run`: print the command instead of running it.
+    dry_run: bool,
+    /// `--wslgit-no-path-translation`: pass the arguments and output unchanged.
+    no_path_translation: bool,
+}
+
+/// Split the wslgit-only flags off the command line `args`, so the
+/// subcommand is found at its position for git. Arguments after a `--` are
+/// paths for git and kept, even if they look like one of the flags.
+fn parse_wslgit_options(args: Vec<String>) -> (Vec<String>, WslgitOptions) {
+    let mut options = WslgitOptions::default();
+    let mut git_args = Vec::with_capacity(args.len());
+    let mut after_dashes = false;
+    for arg in args {
+        if !after_dashes {
+            match arg.as_str() {
+                "--wslgit-dry-run" => {
+                    options.dry_run = true;
+                    continue;
+                }
+                "--wslgit-no-path-translation" => {
+                    options.no_path_translation = true;
+                    continue;
+                }
+                "--" => after_dashes = true,
+                _ => {}
+            }
+        }
+        git_args.push(arg);
+    }
+    (git_args, options)
+}
+
+/// The git program run inside WSL, quoted for the shell: the `setting` of
+/// `WSLGIT_GIT_BIN` if not empty, or else `git`.
+fn git_bin(setting: Option<String>) -> String {
+    match setting.as_deref().map(str::trim) {
+        Some(bin) if !bin.is_empty() => shell_escape(bin.to_owned()),
+        _ => String::from("git"),
+    }
+}
+
+/// The command wrapped by wslgit, quoted for the shell: the Windows command
+/// line of `WSLGIT_CMD` in `cmd_setting`, e.g. `make -C src`, or else the
+/// git program of `git_bin_setting`.
+fn wrapped_command(cmd_setting: Option<String>, git_bin_setting: Option<String>) -> Vec<String> {
+    let command = split_command_line(cmd_setting.as_deref().unwrap_or_default());
+    if command.is_empty() {
+        return vec![git_bin(git_bin_setting)];
+    }
+    command.into_iter().map(shell_escape).collect()
+}
+
+/// Whether the `cmd_setting` of `WSLGIT_CMD` makes wslgit wrap another
+/// command than git, which turns off everything specific to git.
+fn wraps_other_command(cmd_setting: Option<&str>) -> bool {
+    !split_command_line(cmd_setting.unwrap_or_default()).is_empty()
+}
+
+/// The arguments of the command of `WSLGIT_CMD` quoted for the shell inside
+/// WSL, each one translated like an argument of git without a known role.
+fn wsl_wrapped_args(cli_args: &[String], translates_paths: bool, translator: &Translator)
+                    -> Result<Vec<String>, Error> {
+    let mut wrapped_args = vec![];
+    for arg in cli_args {
+        let translated = if translates_paths { translator.to_unix(arg.clone())? } else { arg.clone() };
+        if *VERBOSE && translates_paths {
+            let kind = translator.classify(arg, false);
+            if *arg == translated {
+                log!("{:?}: {:?}, unchanged", arg, kind);
+            } else {
+                log!("{:?}: {:?}, translated to {:?}", arg, kind, translated);
+            }
+        }
+        wrapped_args.push(shell_escape(translated));
+    }
+    Ok(wrapped_args)
+}
+
+/// The arguments of git quoted for the shell inside WSL, with their paths
+/// translated unless `translates_paths` is disabled.
+fn wsl_git_args(git_cli_args: &[String], translates_paths: bool, translator: &Translator)
+                -> Result<Vec<String>, Error> {
+    let git_cli_args = if translates_paths {
+        let translated = translate_git_args(git_cli_args, translator)?;
+        if *VERBOSE {
+            for line in describe_translations(git_cli_args, &translated, translator) {
+                log!("{}", line);
+            }
+        }
+        translated
+    } else {
+        git_cli_args.to_vec()
+    };
+    Ok(git_cli_args.into_iter().map(shell_escape).collect())
+}
+
+/// A line for each of the git arguments `args`, telling how it was
+/// translated to the `translated` one, for `WSLGIT_VERBOSE`.
+fn describe_translations(args: &[String], translated: &[String], translator: &Translator)
+                         -> Vec<String> {
+    args.iter().zip(translated).zip(explain_git_args(args, translator))
+        .map(|((arg, translated), (role, kind))| if arg == translated {
+            format!("{:?}: {:?} {:?}, unchanged", arg, role, kind)
+        } else {
+            format!("{:?}: {:?} {:?}, translated to {:?}", arg, role, kind, translated)
+        })
+        .collect()
+}
+
+/// A readable representation of `command`, with the environment variables
+/// it sets on separate lines before the command line.
+fn format_command(command: &Command) -> String {
+    let quote = |arg: &OsStr| -> String {
+        let arg = arg.to_string_lossy();
+        if arg.is_empty() || arg.contains(char::is_whitespace) {
+            format!("\"{}\"", arg.replace('"', "\\\""))
+        } else {
+            arg.into_owned()
+        }
+    };
+    let mut lines: Vec<String> = command.get_envs()
+        .filter_map(|(key, value)| value.map(
+            |value| format!("{}={}", key.to_string_lossy(), value.to_string_lossy())))
+        .collect();
+    let mut command_line = vec![quote(command.get_program())];
+    command_line.extend(command.get_args().map(quote));
+    lines.push(command_line.join(" "));
+    lines.join("\n")
+}
+
+/// Log the command about to run, with everything influencing it.
+fn debug_command(command: &Command, mode: ExecutionMode) {
+    debug!("mode: {:?}", mode);
+    debug!("distro: {}", WSL_DISTRO.as_deref().unwrap_or("(default)"));
+    debug!("user: {}", WSL_USER.as_deref().unwrap_or("(default)"));
+    if mode == ExecutionMode::Wsl {
+        debug!("WSL version: {}",
+               WSL_VERSION.map_or(String::from("unknown"), |version| format!("{:?}", version)));
+    }
+    if let Some(cwd) = env::current_dir().ok().and_then(|cwd| cwd.to_str().map(String::from)) {
+        match TRANSLATOR.to_unix(cwd.clone()) {
+            Ok(cwd_unix) => debug!("working directory: {} -> {}", cwd, cwd_unix),
+            Err(err) => debug!("working directory: {} ({})", cwd, err),
+        }
+    }
+    for (key, value) in command.get_envs() {
+        debug!("env: {}={}", key.to_string_lossy(),
+               value.map(|value| value.to_string_lossy()).unwrap_or_default());
+    }
+    let argv: Vec<_> = command.get_args().map(OsStr::to_string_lossy).collect();
+    debug!("command: {} {:?}", command.get_program().to_string_lossy(), argv);
+}
+
+/// The command line arguments, which on Windows may contain unpaired
+/// UTF-16 surrogates. These are replaced instead of panicking like
+/// `env::args()`.
+fn cli_args() -> Vec<String> {
+    env::args_os()
+        .map(|arg| arg.into_string().unwrap_or_else(|arg| {
+            log!("replacing invalid characters in the argument {:?}", arg);
+            arg.to_string_lossy().into_owned()
+        }))
+        .collect()
+}
+
+fn run() -> Result<i32, Error> {
     let mut args: Vec<String> = vec![];
     let mut proc_setup;
     let mut opt_transform_output: Option<fn(String) -> String> = None;
+    let mut opt_transform_errors: Option<fn(String) -> String> = None;
+    let mut uses_stdin = true;
+    let mut drops_shell_noise = false;
+    // removed once git is done
+    let mut temp_script: Option<TempFile> = None;
 
-    if env::args().nth(1).unwrap_or_default() == "win-cmd" {
+    let (cli_args, options) = parse_wslgit_options(cli_args());
+    let dry_run = options.dry_run;
+    let translates_paths = !options.no_path_translation;
+    let mode = execution_mode(cli_args.get(1).map(String::as_str), is_inside_wsl());
+    if mode == ExecutionMode::Doctor {
+        doctor::print_report();
+        return Ok(0);
+    }
+    if mode == ExecutionMode::InstallCredentialHelper {
+        return credential::install(dry_run);
+    }
+    if mode == ExecutionMode::Native {
+        let self_exe = env::current_exe().ok();
+        proc_setup = Command::new(find_native_git(
+            &env::var_os("PATH").unwrap_or_default(), self_exe.as_deref()));
+        args.extend(cli_args.iter().skip(1).cloned());
+    } else if mode == ExecutionMode::WinCmd {
         proc_setup = Command::new("cmd");
         args.push(String::from("/c"));
-        args.extend(env::args().skip(2).map(translate_path_to_win));
+        args.extend(cli_args.iter().skip(2).cloned().map(
+            |arg| TRANSLATOR.to_win_path(arg)));
     } else {
-        proc_setup = Command::new("wsl");
-        args.push(String::from("git"));
-        args.extend(env::args().skip(1).map(translate_path_to_unix));
+        proc_setup = wsl();
+        proc_setup.args(wsl_options(WSL_DISTRO.as_deref(), WSL_USER.as_deref(), &WSL_ARGS));
+        let cwd_setting = config_var("WSLGIT_CWD");
+        let is_cwd_overridden = cwd_setting.as_deref().is_some_and(|cwd| !cwd.trim().is_empty());
+        let git_cwd = git_working_dir(cwd_setting, env::current_dir());
+        // also inherited by `wsl` without `--cd`
+        if is_cwd_overridden {
+            if let Ok(ref cwd) = git_cwd {
+                if cwd.is_dir() {
+                    proc_setup.current_dir(cwd);
+                } else {
+                    log!("the working directory {} from WSLGIT_CWD does not exist", cwd.display());
+                }
+            }
+        }
+        if uses_wsl_cd() {
+            let cwd = working_dir(git_cwd);
+            proc_setup.args(wsl_cd_args(cwd.as_deref(), &TRANSLATOR));
+        }
 
-        // add git commands that must use translate_path_to_win
-        if arg_matching(is_translated_command) {
-            opt_transform_output = Some(translate_path_to_win_output);
+        // add git commands whose output must be translated with Translator::to_win
+        let git_cli_args: Vec<String> = cli_args.iter().skip(1).cloned().collect();
+        let cmd_setting = config_var("WSLGIT_CMD");
+        let wraps_git = !wraps_other_command(cmd_setting.as_deref());
+        if wraps_git && translates_paths && is_translated_invocation(&git_cli_args) {
+            let transform: fn(String) -> String = if env_flag("WSLGIT_JSON_OUTPUT") {
+                |line| TRANSLATOR.to_win_json(line)
+            } else if is_mount_rooted_invocation(&git_cli_args) {
+                |line| TRANSLATOR.to_win_mounted(line)
+            } else {
+                |line| TRANSLATOR.to_win(line)
+            };
+            opt_transform_output = Some(transform);
+            if translates_stderr() {
+                opt_transform_errors = Some(transform);
+            }
         }
-        if arg_matching(is_version_command) {
+        if wraps_git && is_version_invocation(&git_cli_args) {
             opt_transform_output = Some(append_version);
         }
 
-        let wslgit_cmd = translate_path_to_unix(env::args().nth(0).expect("Cannot find args[0]"));
-        let mut wsl_env: Vec<String> = vec![];
-        for (ref env_key, ref env_val) in env::vars() {
-            if env_key.starts_with("GIT_") {
-                if env_key == "GIT_EDITOR" {
-                    proc_setup.env("GIT_EDITOR", format!("{} win-cmd {}", wslgit_cmd, env_val));
-                } else {
-                    proc_setup.env(env_key, env_val);
-                }
-                wsl_env.push(format!("{}/u", env_key));
-            }
+        let mut git_args = wrapped_command(cmd_setting, config_var("WSLGIT_GIT_BIN"));
+        if wraps_git {
+            git_args.extend(captured_output_options(
+                opt_transform_output.is_some(), &get_color_mode(), io::stdout().is_terminal()));
+            git_args.extend(wsl_git_args(&git_cli_args, translates_paths, &TRANSLATOR)?);
+        } else {
+            git_args.extend(wsl_wrapped_args(&git_cli_args, translates_paths, &TRANSLATOR)?);
         }
-        if !wsl_env.is_empty() {
-            proc_setup.env("WSLENV", wsl_env.join(":"));
+        let shell_mode = get_shell_mode();
+        args = wsl_command_args(&shell_mode, git_args.clone());
+        drops_shell_noise = shell_mode != ShellMode::Direct;
+        let wsl_args: Vec<String> = proc_setup.get_args()
+            .map(|arg| arg.to_string_lossy().into_owned())
+            .chain(args.iter().cloned())
+            .collect();
+        if exceeds_command_line_limit(&wsl_args) {
+            let script = TempFile::create(&env::temp_dir(), "wslgit", &format!("{}\n", git_args.join(" ")))
+                .map_err(Error::TempFile)?;
+            let script_unix = TRANSLATOR.to_unix(script.0.to_string_lossy().into_owned())?;
+            debug!("command line too long, running git from {}", script_unix);
+            args = wsl_script_args(&shell_mode, &script_unix);
+            temp_script = Some(script);
         }
+        uses_stdin = !wraps_git || needs_stdin(&git_cli_args);
+
+        // args[0] may lack the directory and extension when found via PATH
+        let wslgit_exe = env::current_exe().ok()
+            .and_then(|exe| exe.to_str().map(String::from))
+            .unwrap_or_else(|| cli_args.first().cloned().unwrap_or_default());
+        let wslgit_cmd = TRANSLATOR.to_unix(wslgit_exe)?;
+        proc_setup.envs(wsl_git_env(
+            &wslgit_cmd, &FORWARDED_VARIABLES, &TRANSLATOR, env::vars()));
     }
 
     // setup the git subprocess launched inside WSL
     proc_setup
         .args(&args)
-        .stdin(Stdio::inherit())
+        .stdin(if uses_stdin { Stdio::inherit() } else { Stdio::null() })
         .stdout(if opt_transform_output.is_some() { Stdio::piped() } else { Stdio::inherit() })
-        .stderr(Stdio::inherit());
+        .stderr(if opt_transform_errors.is_some() || drops_shell_noise {
+            Stdio::piped()
+        } else {
+            Stdio::inherit()
+        });
+
+    if *DEBUG {
+        debug_command(&proc_setup, mode);
+    }
+    if dry_run {
+        println!("{}", format_command(&proc_setup));
+        return Ok(0);
+    }
 
     let mut child = proc_setup
         .spawn()
-        .expect(&format!("Failed to execute command '{}'", args.join(" ")));
+        .map_err(|err| Error::Spawn(proc_setup.get_program().to_string_lossy().into_owned(), err))?;
+
+    // read stderr concurrently, the child blocks if either pipe is full
+    let stderr_forwarding = child.stderr.take().map(|child_stderr| {
+        let keeps_line: fn(&str) -> bool =
+            if drops_shell_noise { |line| !is_shell_noise(line, STDERR_FILTER.as_ref()) } else { |_| true };
+        forward_in_background(child_stderr, io::stderr(),
+                              opt_transform_errors.unwrap_or(|line| line), keeps_line)
+    });
 
     if let Some(transform_output) = opt_transform_output {
         if let Some(ref mut child_stdout) = child.stdout {
-            let child_stdout = BufReader::new(child_stdout);
-            let mut stdout = io::stdout();
-            let lines_iter = child_stdout.lines().filter_map(|l| l.ok());
-            for line in lines_iter {
-                stdout.write_all(transform_output(line).as_bytes()).ok();
+            let stdout = io::stdout();
+            if let Err(err) = forward_output(
+                BufReader::new(child_stdout), &mut stdout.lock(), transform_output) {
+                log!("failed to forward the output of git: {}", err);
             }
-            stdout.flush().expect("Failed to flush output");
         }
     }
 
-    let status = child.wait().expect("Failed to wait for command");
+    if let Some(stderr_forwarding) = stderr_forwarding {
+        match stderr_forwarding.join() {
+            Ok(Ok(_)) => {}
+            Ok(Err(err)) => log!("failed to forward the errors of git: {}", err),
+            Err(_) => log!("failed to forward the errors of git"),
+        }
+    }
+
+    let status = child.wait().map_err(Error::Wait)?;
+    drop(temp_script);
     // forward any exit code
-    if let Some(exit_code) = status.code() {
-        std::process::exit(exit_code);
+    let exit_code = forwarded_exit_code(status);
+    if exit_code == WSL_FAILURE_EXIT_CODE && mode == ExecutionMode::Wsl {
+        match *WSL_DISTRO {
+            None if is_default_distro_missing() => return Err(Error::NoDistro),
+            Some(ref distro) if !is_distro_registered(distro) =>
+                return Err(Error::UnregisteredDistro(distro.clone())),
+            _ => {}
+        }
+        if let Some(ref user) = *WSL_USER {
+            log!("failed to run git as the WSL user '{}' from WSLGIT_USER", user);
+        }
     }
+    Ok(exit_code)
 }
 
+fn main() {
+    match run() {
+        Ok(exit_code) => std::process::exit(exit_code),
+        Err(err) => {
+            log!("{}", err);
+            std::process::exit(err.exit_code());
+        }
+    }
+}
+
+
+#[cfg(test)]
+fn args(args: &[&str]) -> Vec<String> {
+    args.iter().map(|arg| arg.to_string()).collect()
+}
 
 #[test]
-fn win_to_unix_path_trans() {
+fn wsl_command_in_shell_modes() {
+    let git_args = |args: &[&str]| -> Vec<String> {
+        args.iter().map(|arg| shell_escape(arg.to_string())).collect()
+    };
+    assert_eq!(
+        wsl_command_args(&ShellMode::Direct, git_args(&["git", "status"])),
+        vec!["git", "status"]);
+    assert_eq!(
+        wsl_command_args(&ShellMode::Login, git_args(&["git", "commit", "-m", "it's $HOME"])),
+        vec!["-e", "bash", "-lc", "git commit -m 'it'\\''s $HOME'"]);
+    assert_eq!(
+        wsl_command_args(&ShellMode::Login, git_args(&["git", "add", "/mnt/c/a b/*.rs", ""])),
+        vec!["-e", "bash", "-lc", "git add '/mnt/c/a b/*.rs' ''"]);
     assert_eq!(
-        translate_path_to_unix("d:\\test\\file.txt".to_string()),
-        "/mnt/d/test/file.txt");
+        wsl_command_args(&ShellMode::Interactive,
+                         git_args(&["git", "log", "--format=%h \"%s\"", "C:\\a;b"])),
+        vec!["-e", "bash", "-lic", "git log '--format=%h \"%s\"' 'C:\\a;b'"]);
+    assert_eq!(wsl_script_args(&ShellMode::Interactive, "/tmp/wslgit.sh"),
+               vec!["-e", "bash", "-li", "/tmp/wslgit.sh"]);
+
+    assert!(is_shell_noise("bash: cannot set terminal process group (42): Inappropriate ioctl for device",
+                           None));
+    assert!(is_shell_noise("bash: no job control in this shell", None));
+    assert!(!is_shell_noise("fatal: not a git repository", None));
+    let motd = Regex::new("^(Welcome to |Last login: )").unwrap();
+    assert!(is_shell_noise("Welcome to Ubuntu 22.04.3 LTS (GNU/Linux 5.15.0 x86_64)", Some(&motd)));
+    assert!(!is_shell_noise("Welcome to Ubuntu 22.04.3 LTS (GNU/Linux 5.15.0 x86_64)", None));
+    assert!(!is_shell_noise("fatal: not a git repository", Some(&motd)));
+}
+
+#[test]
+fn long_command_line_via_script() {
+    let paths = |count: usize| -> Vec<String> {
+        (0..count).map(|i| format!("/mnt/c/repo/src/file{:05}.rs", i)).collect()
+    };
+    // 28 characters per path, plus the separator and quotes
+    assert!(!exceeds_command_line_limit(&paths(10)));
+    assert!(!exceeds_command_line_limit(&paths(1000)));
+    assert!(exceeds_command_line_limit(&paths(1100)));
+    // characters outside the BMP count twice
+    assert!(exceeds_command_line_limit(&["\u{1F600}".repeat(16384)]));
+    assert_eq!(wsl_script_args(&ShellMode::Direct, "/mnt/c/Temp/wslgit-1.sh"),
+               vec!["-e", "sh", "/mnt/c/Temp/wslgit-1.sh"]);
+    assert_eq!(wsl_script_args(&ShellMode::Login, "/mnt/c/Temp/wslgit-1.sh"),
+               vec!["-e", "bash", "-l", "/mnt/c/Temp/wslgit-1.sh"]);
+
+    // each script gets a file of its own, removed afterwards
+    let dir = env::temp_dir();
+    let first = TempFile::create(&dir, "wslgit-test", "git status\n").unwrap();
+    let second = TempFile::create(&dir, "wslgit-test", "git log\n").unwrap();
+    assert_ne!(first.0, second.0);
+    assert_eq!(fs::read_to_string(&first.0).unwrap(), "git status\n");
+    let path = first.0.clone();
+    drop(first);
+    assert!(!path.exists());
+}
+
+#[test]
+fn wsl_exe_location() {
+    let system_root = Path::new("C:\\Windows");
+    let path_var = env::join_paths(["/usr/bin", "/opt/bin"]).unwrap();
+    assert_eq!(wsl_exe_candidates(&path_var, system_root), vec![
+        Path::new("/usr/bin").join("wsl.exe"),
+        Path::new("/opt/bin").join("wsl.exe"),
+        system_root.join("System32").join("wsl.exe"),
+        system_root.join("Sysnative").join("wsl.exe"),
+    ]);
+    assert_eq!(wsl_exe_candidates(OsStr::new(""), system_root).len(), 2);
+
+    let root = env::temp_dir().join(format!("wslgit-test-wsl-exe-{}", std::process::id()));
+    fs::create_dir_all(root.join("Sysnative")).unwrap();
+    assert_eq!(find_wsl_exe(wsl_exe_candidates(OsStr::new(""), &root)), None);
+    fs::write(root.join("Sysnative").join("wsl.exe"), "").unwrap();
+    assert_eq!(find_wsl_exe(wsl_exe_candidates(OsStr::new(""), &root)),
+               Some(root.join("Sysnative").join("wsl.exe")));
+    fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn registered_distro_list() {
+    let list_output: Vec<u8> = "\u{feff}Ubuntu-20.04\r\nDebian\r\n\r\n"
+        .encode_utf16()
+        .flat_map(|unit| vec![unit as u8, (unit >> 8) as u8])
+        .collect();
     assert_eq!(
-        translate_path_to_unix("C:\\Users\\test\\a space.txt".to_string()),
-        "/mnt/c/Users/test/a space.txt");
+        parse_distro_list(&decode_wsl_output(&list_output)),
+        vec!["Ubuntu-20.04", "Debian"]);
+    // with `WSL_UTF8=1`
+    assert_eq!(parse_distro_list(&decode_wsl_output(b"Ubuntu-20.04\r\nDebian\r\n")),
+               vec!["Ubuntu-20.04", "Debian"]);
+    assert!(parse_distro_list("").is_empty());
+}
+
+#[test]
+fn missing_distros() {
+    assert!(lists_no_distros(true, ""));
+    assert!(lists_no_distros(true, "\u{feff}\r\n\0"));
+    // with no distro installed, `wsl` prints a message and fails
+    assert!(lists_no_distros(false, "Windows Subsystem for Linux has no installed distributions.\r\n"));
+    assert!(!lists_no_distros(true, "Ubuntu\r\n"));
+}
+
+#[test]
+fn verbose_distro_list() {
+    let list_output: Vec<u8> = "\u{feff}  NAME            STATE           VERSION\r\n\
+                                * Ubuntu-20.04    Running         2\r\n  \
+                                Debian          Stopped         1\r\n\r\n"
+        .encode_utf16()
+        .flat_map(|unit| vec![unit as u8, (unit >> 8) as u8])
+        .collect();
+    let distros = parse_distro_list_verbose(&decode_wsl_output(&list_output));
+    assert_eq!(distros, vec![
+        DistroInfo { name: "Ubuntu-20.04".to_owned(), version: Some(WslVersion::Wsl2), is_default: true },
+        DistroInfo { name: "Debian".to_owned(), version: Some(WslVersion::Wsl1), is_default: false },
+    ]);
+    assert!(parse_distro_list_verbose("").is_empty());
+
+    assert_eq!(find_distro(&distros, None).map(|distro| distro.version), Some(Some(WslVersion::Wsl2)));
+    assert_eq!(find_distro(&distros, Some("debian")).map(|distro| distro.version),
+               Some(Some(WslVersion::Wsl1)));
+    assert_eq!(find_distro(&distros, Some("Alpine")), None);
+    assert_eq!(find_distro(&[], None), None);
 }
 
 #[test]
-fn unix_to_win_path_trans() {
+fn configured_translated_commands() {
+    let defaults = ["rev-parse", "remote"];
+    assert_eq!(merge_config_list(&defaults, ""), vec!["rev-parse", "remote"]);
     assert_eq!(
-        translate_path_to_win_output("/mnt/d/some path/a file.md".to_string()),
-        "d:/some path/a file.md".to_string());
+        merge_config_list(&defaults, "ls-files, worktree,,remote"),
+        vec!["rev-parse", "remote", "ls-files", "worktree"]);
     assert_eq!(
-        translate_path_to_win_output("origin  /mnt/c/path/ (fetch)".to_string()),
-        "origin  c:/path/ (fetch)");
-    let multiline = "mirror  /mnt/c/other/ (fetch)\nmirror  /mnt/c/other/ (push)\n";
-    let multiline_result = "mirror  c:/other/ (fetch)\nmirror  c:/other/ (push)\n";
+        merge_config_list(&defaults, "-remote,config"),
+        vec!["rev-parse", "config"]);
     assert_eq!(
-        &*translate_path_to_win_output(String::from(multiline)),
-        &multiline_result[..]);
+        merge_config_list(TRANSLATED_CMDS, "-remote"),
+        vec!["rev-parse", "check-ignore", "check-attr", "ls-files", "reflog"]);
 }
 
 #[test]
-fn no_path_translation() {
-    assert_eq!(
-        &*translate_path_to_win_output(String::from("/mnt/other/file.sh")),
-        String::from("/mnt/other/file.sh"));
+fn subcommand_after_global_options() {
+    assert_eq!(find_subcommand(&args(&["status"])), Some(0));
+    assert_eq!(find_subcommand(&args(&["-c", "core.quotepath=false", "-C", "C:\\repo", "rev-parse"])),
+               Some(4));
+    assert_eq!(find_subcommand(&args(&["--git-dir", "remote", "status", "remote"])), Some(2));
+    assert_eq!(find_subcommand(&args(&["--git-dir=C:\\repo\\.git", "-p", "--no-pager", "log"])),
+               Some(3));
+    assert_eq!(find_subcommand(&args(&["--work-tree", "rev-parse", "--namespace", "ns", "log"])),
+               Some(4));
+    assert_eq!(find_subcommand(&args(&["--version"])), None);
+    assert_eq!(find_subcommand(&args(&[])), None);
+
+    assert!(is_translated_invocation(&args(&["-C", "C:\\repo", "rev-parse", "--show-toplevel"])));
+    assert!(is_translated_invocation(
+        &args(&["--git-dir=C:\\repo\\.git", "--work-tree", "C:\\repo", "rev-parse", "--show-cdup"])));
+    assert!(!is_translated_invocation(&args(&["log", "remote"])));
+    assert!(!is_translated_invocation(&args(&["-c", "remote", "status"])));
+    assert!(is_translated_invocation(&args(&["status", "--porcelain"])));
+    assert!(is_translated_invocation(&args(&["-C", "C:\\repo", "status", "--porcelain=v2", "-b"])));
+    assert!(is_translated_invocation(&args(&["status", "-s"])));
+    assert!(is_translated_invocation(&args(&["status", "-z", "-uall"])));
+    assert!(!is_translated_invocation(&args(&["status", "--", "-z"])));
+    assert!(!is_translated_invocation(&args(&["log", "--porcelain"])));
+    assert!(!is_translated_invocation(&args(&["log", "--name-status"])));
+    assert!(is_translated_invocation(&args(&["stash", "list"])));
+    assert!(!is_translated_invocation(&args(&["stash", "push", "-p"])));
+    assert!(!is_translated_invocation(&args(&["notes", "edit"])));
+    // only the forms printing output, not ones with an editor or progress
+    assert!(is_translated_invocation(&args(&["config", "--show-origin", "--get-all", "include.path"])));
+    assert!(is_translated_invocation(&args(&["config", "-l"])));
+    assert!(!is_translated_invocation(&args(&["config", "--edit"])));
+    assert!(!is_translated_invocation(&args(&["config", "--global", "-e", "--show-origin"])));
+    assert!(is_translated_invocation(&args(&["submodule", "status", "--recursive"])));
+    assert!(!is_translated_invocation(&args(&["submodule", "update", "--init"])));
+    assert!(is_translated_invocation(&args(&["worktree", "list", "--porcelain"])));
+    assert!(!is_translated_invocation(&args(&["worktree", "add", "C:\\wt"])));
+    assert!(is_mount_rooted_invocation(&args(&["-C", "C:\\repo", "reflog", "show"])));
+    assert!(is_mount_rooted_invocation(&args(&["notes", "list"])));
+    assert!(!is_mount_rooted_invocation(&args(&["rev-parse", "--show-toplevel"])));
+    assert!(is_translated_invocation(&args(&["log", "--format=%H %s", "--name-status"])));
+    assert!(is_translated_invocation(&args(&["log", "--pretty", "oneline"])));
+    assert!(!is_translated_invocation(&args(&["log", "-p", "--format=%H"])));
+    assert!(!is_translated_invocation(&args(&["log", "--", "--format=%H"])));
+    assert!(is_version_invocation(&args(&["--version"])));
+    assert!(is_version_invocation(&args(&["-c", "a=b", "version"])));
+    assert!(!is_version_invocation(&args(&["log", "--grep", "version"])));
+}
+
+#[test]
+fn git_env_with_sequence_editor() {
+    let vars = vec![
+        ("PATH".to_owned(), "C:\\Windows".to_owned()),
+        ("GIT_SEQUENCE_EDITOR".to_owned(), "C:\\Tools\\ed.exe --wait".to_owned()),
+        ("GIT_AUTHOR_NAME".to_owned(), "Jane Doe".to_owned()),
+    ];
+    let git_env = wsl_git_env("/mnt/c/wslgit.exe", &[], &Translator::default(), vars);
+    assert_eq!(git_env, vec![
+        ("GIT_SEQUENCE_EDITOR".to_owned(), "/mnt/c/wslgit.exe win-cmd 'C:\\Tools\\ed.exe' --wait".to_owned()),
+        ("GIT_AUTHOR_NAME".to_owned(), "Jane Doe".to_owned()),
+        ("WSLENV".to_owned(), "GIT_SEQUENCE_EDITOR/u:GIT_AUTHOR_NAME/u".to_owned()),
+    ]);
+    assert!(wsl_git_env("/mnt/c/wslgit.exe", &[], &Translator::default(), vec![]).is_empty());
+}
+
+#[test]
+fn git_env_with_forwarded_variables() {
+    let forwarded = merge_config_list(FORWARDED_VARS, "SSL_CERT_FILE,-no_proxy");
+    let vars = vec![
+        ("WSLENV".to_owned(), "USERPROFILE/p:GIT_SSL_CAINFO/p".to_owned()),
+        ("https_proxy".to_owned(), "http://proxy:3128".to_owned()),
+        ("no_proxy".to_owned(), "localhost".to_owned()),
+        ("SSL_CERT_FILE".to_owned(), "C:\\certs\\ca.pem".to_owned()),
+        ("GIT_SSL_CAINFO".to_owned(), "\\\\wsl$\\Ubuntu\\etc\\ca.pem".to_owned()),
+        ("GIT_DIR".to_owned(), "\\\\server\\share\\.git".to_owned()),
+        ("GIT_ALTERNATE_OBJECT_DIRECTORIES".to_owned(), "C:\\a\\objects;D:\\b\\objects".to_owned()),
+    ];
+    let git_env = wsl_git_env("/mnt/c/wslgit.exe", &forwarded, &Translator::default(), vars);
+    assert_eq!(git_env, vec![
+        ("https_proxy".to_owned(), "http://proxy:3128".to_owned()),
+        ("SSL_CERT_FILE".to_owned(), "/mnt/c/certs/ca.pem".to_owned()),
+        ("GIT_SSL_CAINFO".to_owned(), "/etc/ca.pem".to_owned()),
+        ("GIT_DIR".to_owned(), "\\\\server\\share\\.git".to_owned()),
+        ("GIT_ALTERNATE_OBJECT_DIRECTORIES".to_owned(), "/mnt/c/a/objects:/mnt/d/b/objects".to_owned()),
+        ("WSLENV".to_owned(),
+         "USERPROFILE/p:https_proxy/u:SSL_CERT_FILE/u:GIT_SSL_CAINFO/u:GIT_DIR/u:\
+          GIT_ALTERNATE_OBJECT_DIRECTORIES/u".to_owned()),
+    ]);
+}
+
+#[test]
+fn git_env_with_path_variables() {
+    let vars = vec![
+        ("GIT_CONFIG_GLOBAL".to_owned(), "C:\\Users\\me\\.gitconfig".to_owned()),
+        ("GIT_CONFIG_SYSTEM".to_owned(), "\\\\wsl$\\Ubuntu\\etc\\gitconfig".to_owned()),
+        ("GIT_DIR".to_owned(), "..\\repo\\.git".to_owned()),
+        ("GIT_WORK_TREE".to_owned(), "src\\tree".to_owned()),
+        ("GIT_OBJECT_DIRECTORY".to_owned(), "D:\\objects".to_owned()),
+        ("GIT_INDEX_FILE".to_owned(), "".to_owned()),
+    ];
+    let git_env = wsl_git_env("/mnt/c/wslgit.exe", &[], &Translator::default(), vars);
+    assert_eq!(git_env, vec![
+        ("GIT_CONFIG_GLOBAL".to_owned(), "/mnt/c/Users/me/.gitconfig".to_owned()),
+        ("GIT_CONFIG_SYSTEM".to_owned(), "/etc/gitconfig".to_owned()),
+        ("GIT_DIR".to_owned(), "../repo/.git".to_owned()),
+        ("GIT_WORK_TREE".to_owned(), "src/tree".to_owned()),
+        ("GIT_OBJECT_DIRECTORY".to_owned(), "/mnt/d/objects".to_owned()),
+        ("GIT_INDEX_FILE".to_owned(), "".to_owned()),
+        ("WSLENV".to_owned(), "GIT_CONFIG_GLOBAL/u:GIT_CONFIG_SYSTEM/u:GIT_DIR/u:GIT_WORK_TREE/u:\
+          GIT_OBJECT_DIRECTORY/u:GIT_INDEX_FILE/u".to_owned()),
+    ]);
+    // `/dev/null` disables the configuration file
+    let vars = vec![("GIT_CONFIG_GLOBAL".to_owned(), "/dev/null".to_owned())];
+    assert_eq!(wsl_git_env("/mnt/c/wslgit.exe", &[], &Translator::default(), vars)[0].1, "/dev/null");
+}
+
+#[test]
+fn git_env_with_askpass() {
+    let forwarded = merge_config_list(FORWARDED_VARS, "");
+    let vars = vec![
+        ("GIT_ASKPASS".to_owned(), "C:\\Program Files\\Git\\mingw64\\bin\\git-askpass.exe".to_owned()),
+        ("SSH_ASKPASS".to_owned(), "d:/tools/askpass.exe".to_owned()),
+        ("SSH_ASKPASS_REQUIRE".to_owned(), "force".to_owned()),
+    ];
+    let git_env = wsl_git_env("/mnt/c/wslgit.exe", &forwarded, &Translator::default(), vars);
+    assert_eq!(git_env, vec![
+        ("GIT_ASKPASS".to_owned(), "/mnt/c/Program Files/Git/mingw64/bin/git-askpass.exe".to_owned()),
+        ("SSH_ASKPASS".to_owned(), "/mnt/d/tools/askpass.exe".to_owned()),
+        ("SSH_ASKPASS_REQUIRE".to_owned(), "force".to_owned()),
+        ("WSLENV".to_owned(), "GIT_ASKPASS/u:SSH_ASKPASS/u:SSH_ASKPASS_REQUIRE/u".to_owned()),
+    ]);
+}
+
+#[test]
+fn forwarded_output_contains_only_git_output() {
+    let mut output: Vec<u8> = vec![];
+    forward_output(
+        "origin\t/mnt/c/repo (fetch)\norigin\t/mnt/c/repo (push)\n".as_bytes(), &mut output,
+        |line| Translator::default().to_win(line))
+        .expect("Failed to forward output");
+    assert_eq!(String::from_utf8(output).unwrap(), "origin\tc:/repo (fetch)\norigin\tc:/repo (push)\n");
+}
+
+#[test]
+fn forwarded_output_keeps_crlf() {
+    let mut output: Vec<u8> = vec![];
+    forward_output(
+        "/mnt/c/path\r\norigin\t/mnt/c/repo (fetch)\r\n\r\n/mnt/d/x\r".as_bytes(), &mut output,
+        |line| Translator::default().to_win(line))
+        .expect("Failed to forward output");
+    assert_eq!(String::from_utf8(output).unwrap(), "c:/path\r\norigin\tc:/repo (fetch)\r\n\r\nd:/x\r");
+    assert_eq!(Translator::default().to_win("/mnt/c/a b\r\n".to_owned()), "c:/a b\r\n");
+}
+
+#[test]
+fn exit_code_of_terminated_child() {
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::ExitStatusExt;
+        assert_eq!(forwarded_exit_code(ExitStatus::from_raw(3 << 8)), 3);
+        // terminated by SIGKILL, so there is no exit code
+        assert_eq!(ExitStatus::from_raw(9).code(), None);
+        assert_eq!(forwarded_exit_code(ExitStatus::from_raw(9)), 137);
+    }
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::ExitStatusExt;
+        assert_eq!(forwarded_exit_code(ExitStatus::from_raw(3)), 3);
+    }
+}
+
+#[test]
+fn streamed_output_translation() {
+    lazy_static! {
+        static ref DEFAULT_TRANSLATOR: Translator = Translator::default();
+    }
+    fn translate_default(line: String) -> String {
+        DEFAULT_TRANSLATOR.to_win(line)
+    }
+    let mut input = vec![];
+    let mut expected = vec![];
+    for i in 0..20000 {
+        input.extend(format!("{} /mnt/c/repo/file{}.txt\n", i, i).into_bytes());
+        expected.extend(format!("{} c:/repo/file{}.txt\n", i, i).into_bytes());
+    }
+    input.extend(b"\xff /mnt/c/invalid\n/mnt/other/file\n/mnt/d/last");
+    expected.extend(b"\xff /mnt/c/invalid\n/mnt/other/file\nd:/last");
+    let mut output: Vec<u8> = vec![];
+    forward_output(&input[..], &mut output, translate_default).expect("Failed to forward output");
+    assert!(output == expected);
+}
+
+#[test]
+fn output_split_across_reads_translation() {
+    /// Returns one of the chunks for each read, like a pipe.
+    struct ChunkedReader(Vec<Vec<u8>>);
+    impl Read for ChunkedReader {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if self.0.is_empty() {
+                return Ok(0);
+            }
+            let chunk = self.0.remove(0);
+            buf[..chunk.len()].copy_from_slice(&chunk);
+            Ok(chunk.len())
+        }
+    }
+    lazy_static! {
+        static ref DEFAULT_TRANSLATOR: Translator = Translator::default();
+    }
+    fn translate_default(line: String) -> String {
+        DEFAULT_TRANSLATOR.to_win(line)
+    }
+    let forward = |chunks: Vec<Vec<u8>>| -> Vec<u8> {
+        let mut output = vec![];
+        forward_output(BufReader::new(ChunkedReader(chunks)), &mut output, translate_default)
+            .expect("Failed to forward output");
+        output
+    };
+    assert_eq!(forward(vec![b"a /mnt/c/re".to_vec(), b"po/file\n/mn".to_vec(), b"t/d/x".to_vec()]),
+               b"a c:/repo/file\nd:/x");
+
+    // a line without line breaks, with paths across the parts it is split into
+    let mut line = vec![];
+    while line.len() < 2 * MAX_LINE_PART {
+        line.extend_from_slice(b"word /mnt/c/some/path ");
+    }
+    let expected = translate_default(String::from_utf8(line.clone()).unwrap()).into_bytes();
+    assert_eq!(forward(line.chunks(1000).map(|chunk| chunk.to_vec()).collect()), expected);
+
+    // a single word longer than any part is passed on as it is
+    let mut word = b"/mnt/c/".to_vec();
+    word.extend(vec![b'x'; MAX_WORD_PART + 10]);
+    word.extend_from_slice("\u{e9}\n".as_bytes());
+    let output = forward(word.chunks(4096).map(|chunk| chunk.to_vec()).collect());
+    assert_eq!(output.len(), word.len() - "/mnt/c/".len() + "c:/".len());
+    assert!(output.starts_with(b"c:/xxx") && output.ends_with("x\u{e9}\n".as_bytes()));
+}
+
+#[test]
+fn background_error_translation() {
+    fn translate_default(line: String) -> String {
+        Translator::default().to_win(line)
+    }
+    let child_stderr = io::Cursor::new(b"fatal: '/mnt/c/repo/missing' does not exist\n".to_vec());
+    let errors = forward_in_background(child_stderr, vec![], translate_default, |_| true)
+        .join()
+        .expect("Forwarding thread panicked")
+        .expect("Failed to forward errors");
+    assert_eq!(String::from_utf8(errors).unwrap(), "fatal: 'c:/repo/missing' does not exist\n");
+}
+
+#[test]
+#[cfg(unix)]
+fn flooded_output_and_errors_translation() {
+    lazy_static! {
+        static ref DEFAULT_TRANSLATOR: Translator = Translator::default();
+    }
+    fn translate_default(line: String) -> String {
+        DEFAULT_TRANSLATOR.to_win(line)
+    }
+    // far more than the pipe buffers on both streams, like a large `git log -p`
+    let mut child = Command::new("sh")
+        .args(["-c", "i=0; while [ $i -lt 20000 ]; do \
+                      echo /mnt/c/out/$i; echo /mnt/c/err/$i >&2; i=$((i+1)); done"])
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("Failed to start sh");
+    let stderr_forwarding = forward_in_background(
+        child.stderr.take().unwrap(), vec![], translate_default, |_| true);
+    let mut output = vec![];
+    forward_output(BufReader::new(child.stdout.take().unwrap()), &mut output, translate_default)
+        .expect("Failed to forward output");
+    let errors = stderr_forwarding.join()
+        .expect("Forwarding thread panicked")
+        .expect("Failed to forward errors");
+    assert!(child.wait().unwrap().success());
+
+    let output = String::from_utf8(output).unwrap();
+    let errors = String::from_utf8(errors).unwrap();
+    assert_eq!(output.lines().count(), 20000);
+    assert_eq!(errors.lines().count(), 20000);
+    assert_eq!(output.lines().last(), Some("c:/out/19999"));
+    assert_eq!(errors.lines().next(), Some("c:/err/0"));
+}
+
+#[test]
+fn stdin_only_closed_for_version() {
+    assert!(!needs_stdin(&args(&["--version"])));
+    assert!(!needs_stdin(&args(&["-c", "core.pager=", "version"])));
+    assert!(needs_stdin(&args(&["commit", "-F", "-"])));
+    assert!(needs_stdin(&args(&["log", "--grep", "--version"])));
+    assert!(needs_stdin(&args(&[])));
 }
 
 #[test]
-fn relative_path_translation() {
+fn no_pager_for_captured_output() {
+    assert_eq!(captured_output_options(true, &ColorMode::Auto, false), vec!["--no-pager"]);
+    assert!(captured_output_options(false, &ColorMode::Auto, false).is_empty());
+}
+
+#[test]
+fn colors_for_captured_output() {
+    let colored = vec!["--no-pager", "-c", "color.ui=always"];
+    assert_eq!(captured_output_options(true, &ColorMode::Auto, true), colored);
+    assert_eq!(captured_output_options(true, &ColorMode::Always, false), colored);
+    assert_eq!(captured_output_options(true, &ColorMode::Never, true), vec!["--no-pager"]);
+    assert!(captured_output_options(false, &ColorMode::Always, true).is_empty());
+
     assert_eq!(
-        translate_path_to_unix(".\\src\\main.rs".to_string()),
-        "./src/main.rs");
+        Translator::default().to_win(
+            "origin\t\x1b[32m/mnt/c/repo\x1b[m (fetch)\x1b[1m/mnt/d/x\x1b[0;31m".to_owned()),
+        "origin\t\x1b[32mc:/repo\x1b[m (fetch)\x1b[1md:/x\x1b[0;31m");
 }
 
 #[test]
-fn long_argument_path_translation() {
+fn execution_mode_inside_wsl() {
+    assert_eq!(execution_mode(Some("status"), false), ExecutionMode::Wsl);
+    assert_eq!(execution_mode(None, false), ExecutionMode::Wsl);
+    assert_eq!(execution_mode(Some("win-cmd"), false), ExecutionMode::WinCmd);
+    assert_eq!(execution_mode(Some("doctor"), false), ExecutionMode::Doctor);
+    assert_eq!(execution_mode(Some("install-credential-helper"), false),
+               ExecutionMode::InstallCredentialHelper);
+    assert_eq!(execution_mode(Some("doctor"), true), ExecutionMode::Native);
+    assert_eq!(execution_mode(Some("status"), true), ExecutionMode::Native);
+    assert_eq!(execution_mode(Some("win-cmd"), true), ExecutionMode::Native);
+
+    let missing_dir = env::temp_dir().join(format!("wslgit-test-no-such-dir-{}", std::process::id()));
+    let path_var = env::join_paths(vec![missing_dir]).unwrap();
+    assert_eq!(find_native_git(&path_var, None), PathBuf::from("/usr/bin/git"));
+
+    let shim_dir = env::temp_dir().join(format!("wslgit-test-native-git-shim-{}", std::process::id()));
+    let git_dir = env::temp_dir().join(format!("wslgit-test-native-git-{}", std::process::id()));
+    for dir in &[&shim_dir, &git_dir] {
+        fs::create_dir_all(dir).unwrap();
+        fs::write(dir.join("git"), "").unwrap();
+    }
+    let path_var = env::join_paths(vec![&shim_dir, &git_dir]).unwrap();
+    assert_eq!(find_native_git(&path_var, Some(&shim_dir.join("git"))), git_dir.join("git"));
+    assert_eq!(find_native_git(&path_var, None), shim_dir.join("git"));
+    for dir in &[&shim_dir, &git_dir] {
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
+
+#[test]
+fn dry_run_flag_and_output() {
+    let (args, options) = parse_wslgit_options(vec!["status".to_owned(), "--wslgit-dry-run".to_owned()]);
+    assert_eq!(args, vec!["status"]);
+    assert!(options.dry_run);
+    assert_eq!(parse_wslgit_options(args.clone()), (args, WslgitOptions::default()));
+
+    let mut command = Command::new("wsl");
+    command.args(["-d", "Ubuntu", "git", "commit", "-m", "'a message'"])
+        .env("GIT_EDITOR", "/mnt/c/wslgit.exe win-cmd vim");
     assert_eq!(
-        translate_path_to_unix("--file=C:\\some\\path.txt".to_owned()),
-        "--file=/mnt/c/some/path.txt");
+        format_command(&command),
+        "GIT_EDITOR=/mnt/c/wslgit.exe win-cmd vim\n\
+         wsl -d Ubuntu git commit -m \"'a message'\"");
+}
+
+#[test]
+fn args_without_path_translation() {
+    let (args, options) = parse_wslgit_options(
+        args(&["wslgit", "--wslgit-no-path-translation", "-C", "C:\\repo", "log", "C:\\file"]));
+    assert!(options.no_path_translation);
+    let git_cli_args = &args[1..];
+    assert_eq!(find_subcommand(git_cli_args), Some(2));
+    assert_eq!(wsl_git_args(git_cli_args, false, &Translator::default()).unwrap(),
+               vec!["-C", "'C:\\repo'", "log", "'C:\\file'"]);
+    assert_eq!(wsl_git_args(git_cli_args, true, &Translator::default()).unwrap(),
+               vec!["-C", "/mnt/c/repo", "log", "/mnt/c/file"]);
+}
+
+#[test]
+fn wslgit_flags_before_subcommand() {
+    let (cli_args, options) = parse_wslgit_options(args(&[
+        "wslgit", "--wslgit-dry-run", "-C", "C:\\repo", "--wslgit-no-path-translation", "ls-files",
+        "--wslgit-dry-run", "--", "--wslgit-no-path-translation"]));
+    assert_eq!(options, WslgitOptions { dry_run: true, no_path_translation: true });
+    assert_eq!(cli_args, args(&["wslgit", "-C", "C:\\repo", "ls-files", "--", "--wslgit-no-path-translation"]));
+    let git_cli_args = &cli_args[1..];
+    assert_eq!(find_subcommand(git_cli_args), Some(2));
+    assert!(is_translated_invocation(git_cli_args));
+    assert_eq!(wsl_git_args(git_cli_args, true, &Translator::default()).unwrap(),
+               vec!["-C", "/mnt/c/repo", "ls-files", "--", "--wslgit-no-path-translation"]);
+
+    // a flag in front of a wslgit command does not hide it
+    let (cli_args, options) = parse_wslgit_options(args(&["wslgit", "--wslgit-dry-run", "doctor"]));
+    assert!(options.dry_run);
+    assert_eq!(execution_mode(cli_args.get(1).map(String::as_str), false), ExecutionMode::Doctor);
+}
+
+#[test]
+fn verbose_translation_lines() {
+    let args = args(&["add", "C:\\a b.txt", "README"]);
+    let translator = Translator::default();
+    let translated = translate_git_args(&args, &translator).unwrap();
+    assert_eq!(describe_translations(&args, &translated, &translator), vec![
+        "\"add\": Other Skipped, unchanged",
+        "\"C:\\\\a b.txt\": Other Drive, translated to \"/mnt/c/a b.txt\"",
+        "\"README\": Other Skipped, unchanged"]);
+}
+
+#[test]
+fn git_bin_override() {
+    assert_eq!(git_bin(None), "git");
+    assert_eq!(git_bin(Some(" ".to_owned())), "git");
+    assert_eq!(git_bin(Some("/usr/local/bin/git".to_owned())), "/usr/local/bin/git");
+    let git_args = vec![git_bin(Some("/opt/my git/git".to_owned())), "status".to_owned()];
+    assert_eq!(wsl_command_args(&ShellMode::Direct, git_args.clone())[0], "'/opt/my git/git'");
+    assert_eq!(wsl_command_args(&ShellMode::Login, git_args)[3], "'/opt/my git/git' status");
+}
+
+#[test]
+fn wrapped_command_override() {
+    assert_eq!(wrapped_command(None, None), vec!["git"]);
+    assert_eq!(wrapped_command(Some(" ".to_owned()), Some("hub".to_owned())), vec!["hub"]);
+    assert_eq!(wrapped_command(Some("make".to_owned()), Some("hub".to_owned())), vec!["make"]);
+    assert_eq!(wrapped_command(Some("make -C \"my dir\"".to_owned()), None),
+               vec!["make", "-C", "'my dir'"]);
+    // the arguments are translated without the rules of git
+    let mut command = wrapped_command(Some("grep".to_owned()), None);
+    command.extend(wsl_wrapped_args(&["-rn".to_owned(), "TODO".to_owned(), "C:\\src".to_owned()],
+                                    true, &Translator::default()).unwrap());
+    assert_eq!(command, vec!["grep", "-rn", "TODO", "/mnt/c/src"]);
+    assert!(wraps_other_command(Some("make")));
+    assert!(!wraps_other_command(Some(" ")));
+    assert!(!wraps_other_command(None));
+}
+
+#[test]
+fn additional_wsl_arguments() {
+    assert_eq!(parse_wsl_args("").unwrap(), Vec::<String>::new());
+    assert_eq!(parse_wsl_args("--shell-type login").unwrap(), vec!["--shell-type", "login"]);
+    assert_eq!(parse_wsl_args(" --system  \"--opt=a b\" ").unwrap(), vec!["--system", "--opt=a b"]);
+    assert_eq!(parse_wsl_args("--shell-type \"login").unwrap_err(), "unterminated quote");
+
+    let extra_args = parse_wsl_args("--shell-type login").unwrap();
+    assert_eq!(wsl_options(Some("Ubuntu"), Some("dev"), &extra_args),
+               vec!["-d", "Ubuntu", "-u", "dev", "--shell-type", "login"]);
+    assert_eq!(wsl_options(None, None, &extra_args), vec!["--shell-type", "login"]);
+    assert!(wsl_options(None, None, &[]).is_empty());
+}
+
+#[test]
+fn working_dir_fallback() {
+    assert_eq!(working_dir(Ok(PathBuf::from("C:\\repo"))), Some("C:\\repo".to_owned()));
+    assert_eq!(working_dir(Err(io::Error::new(io::ErrorKind::NotFound, "deleted"))), None);
+}
+
+#[test]
+fn working_dir_override() {
+    let current_dir = || Ok(PathBuf::from("C:\\Windows\\System32"));
+    assert_eq!(git_working_dir(Some("D:\\repos\\thing".to_owned()), current_dir()).unwrap(),
+               PathBuf::from("D:\\repos\\thing"));
+    assert_eq!(git_working_dir(Some(" D:\\repos ".to_owned()),
+                               Err(io::Error::new(io::ErrorKind::NotFound, "deleted"))).unwrap(),
+               PathBuf::from("D:\\repos"));
+    assert_eq!(git_working_dir(Some(" ".to_owned()), current_dir()).unwrap(),
+               PathBuf::from("C:\\Windows\\System32"));
+    assert_eq!(git_working_dir(None, current_dir()).unwrap(), PathBuf::from("C:\\Windows\\System32"));
+    let cwd = working_dir(git_working_dir(Some("D:\\repos\\thing".to_owned()), current_dir()));
+    assert_eq!(wsl_cd_args(cwd.as_deref(), &Translator::default()), vec!["--cd", "/mnt/d/repos/thing"]);
+}
+
+#[test]
+fn wsl_cd_arguments() {
+    assert_eq!(wsl_cd_args(Some("C:\\Users\\a user\\repo"), &Translator::default()),
+               vec!["--cd", "/mnt/c/Users/a user/repo"]);
+    assert_eq!(wsl_cd_args(Some("\\\\wsl$\\Ubuntu\\home\\user"), &Translator::default()),
+               vec!["--cd", "/home/user"]);
+    assert!(wsl_cd_args(Some("\\\\server\\share\\repo"), &Translator::default()).is_empty());
+    assert!(wsl_cd_args(None, &Translator::default()).is_empty());
+}
+
+#[test]
+fn path_map_parsing() {
+    assert_eq!(parse_path_map("C:\\repos=/home/me/repos, D:/data = /srv/data,"), vec![
+        ("C:\\repos".to_owned(), "/home/me/repos".to_owned()),
+        ("D:/data".to_owned(), "/srv/data".to_owned()),
+    ]);
+    assert!(parse_path_map("").is_empty());
+    assert!(parse_path_map("repos=/home/me/repos,C:\\repos=home,C:\\repos").is_empty());
+}
+
+#[test]
+fn automount_root_from_wsl_conf() {
+    let wsl_conf = "[network]\nroot = /wrong\n\n[automount]\nenabled = true\nroot = /windows/ # comment\n";
+    assert_eq!(parse_automount_root(wsl_conf), Some("/windows".to_owned()));
+    assert_eq!(parse_automount_root("[automount]\nroot = /\n"), Some("".to_owned()));
+    assert_eq!(parse_automount_root("[automount]\nenabled = true\n"), None);
+}
+
+#[test]
+fn drive_mounts_from_proc_mounts() {
+    let proc_mounts = "rootfs / lxfs rw,noatime 0 0\n\
+        C:\\134 /mnt/c 9p rw,dirsync,aname=drvfs;path=C:\\;uid=1000 0 0\n\
+        drvfs /data 9p rw,aname=drvfs;path=Z:\\;symlinkroot=/mnt/ 0 0\n\
+        D: /mnt/my\\040drive drvfs rw,noatime 0 0\n\
+        none /dev tmpfs rw 0 0\n";
+    let drive_mounts = parse_proc_mounts(proc_mounts);
+    assert_eq!(drive_mounts.len(), 3);
+    assert_eq!(drive_mounts["c"], "/mnt/c");
+    assert_eq!(drive_mounts["z"], "/data");
+    assert_eq!(drive_mounts["d"], "/mnt/my drive");
+}
+
+#[test]
+fn mount_root_from_drive_mounts() {
+    let drive_mounts = parse_proc_mounts("C:\\134 /windows/c 9p rw,path=C:\\ 0 0\n\
+                                          D:\\134 /windows/d 9p rw,path=D:\\ 0 0\n\
+                                          Z:\\134 /data 9p rw,path=Z:\\ 0 0\n\
+                                          E: /mnt/e drvfs rw 0 0\n");
+    assert_eq!(mount_root_of(&drive_mounts), Some("/windows".to_owned()));
+    assert_eq!(mount_root_of(&parse_proc_mounts("C: /c drvfs rw 0 0\n")), Some("".to_owned()));
+    assert_eq!(mount_root_of(&parse_proc_mounts("Z: /data drvfs rw 0 0\n")), None);
+    assert_eq!(mount_root_of(&HashMap::new()), None);
+}
+
+#[test]
+fn wsl_probe_parsing() {
+    let probe = parse_probe("5.15.90.1-microsoft-standard-WSL2\n\0C: /mnt/c drvfs rw 0 0\n\0\
+                             [automount]\nroot = /windows/\n\0/home/me\0Ubuntu\n");
+    assert_eq!(probe, WslProbe {
+        os_release: "5.15.90.1-microsoft-standard-WSL2".to_owned(),
+        proc_mounts: "C: /mnt/c drvfs rw 0 0\n".to_owned(),
+        wsl_conf: "[automount]\nroot = /windows/\n".to_owned(),
+        home: "/home/me".to_owned(),
+        distro_name: "Ubuntu".to_owned(),
+    });
+    // `wsl` failed
+    assert_eq!(parse_probe(""), WslProbe::default());
+
+    assert_eq!(kernel_wsl_version(&probe.os_release), Some(WslVersion::Wsl2));
+    assert_eq!(kernel_wsl_version("4.19.104-microsoft-standard"), Some(WslVersion::Wsl2));
+    assert_eq!(kernel_wsl_version("4.4.0-19041-Microsoft"), Some(WslVersion::Wsl1));
+    assert_eq!(kernel_wsl_version(""), None);
 }
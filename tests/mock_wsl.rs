@@ -0,0 +1,321 @@
+//! End-to-end tests running `wslgit` with a stub `wsl`, which records its
+//! arguments and environment and prints the output given by the test.
+#![cfg(unix)]
+
+use std::env;
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::PathBuf;
+use std::process::{Command, Output};
+
+/// The stub of `wsl`: answers the queries of wslgit and otherwise plays git.
+/// Every launch is recorded by its arguments.
+const MOCK_WSL: &str = "#!/bin/sh
+printf '%s\\n' \"$*\" >> \"$MOCK_WSL_DIR/launches\"
+case \"$1\" in
+    --list) printf 'Ubuntu\\n'; exit 0 ;;
+esac
+case \"$*\" in
+    *' sh -c cat /proc/sys/kernel/osrelease;'*) printf '%b' \"$MOCK_WSL_PROBE\"; exit 0 ;;
+esac
+printf '%s\\n' \"$@\" > \"$MOCK_WSL_DIR/args\"
+env > \"$MOCK_WSL_DIR/env\"
+printf '%s' \"$MOCK_WSL_STDOUT\"
+printf '%s' \"$MOCK_WSL_STDERR\" >&2
+exit \"${MOCK_WSL_EXIT:-0}\"
+";
+
+/// A directory with the stub `wsl`, removed at the end of the test.
+struct MockWsl {
+    dir: PathBuf,
+}
+
+impl MockWsl {
+    fn new(name: &str) -> MockWsl {
+        let dir = env::temp_dir().join(format!("wslgit-mock-{}-{}", name, std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let wsl = dir.join("wsl.exe");
+        fs::write(&wsl, MOCK_WSL).unwrap();
+        fs::set_permissions(&wsl, fs::Permissions::from_mode(0o755)).unwrap();
+        MockWsl { dir }
+    }
+
+    /// A `wslgit` command with `args` running the stub, outside of any
+    /// configuration file and not looking like WSL itself.
+    fn wslgit(&self, args: &[&str]) -> Command {
+        let mut wslgit = Command::new(env!("CARGO_BIN_EXE_wslgit-for-jetbrains"));
+        wslgit.args(args)
+            .current_dir(&self.dir)
+            .env_clear()
+            .env("PATH", env::var_os("PATH").unwrap_or_default())
+            .env("MOCK_WSL_DIR", &self.dir)
+            .env("WSLGIT_WSL_EXE", self.dir.join("wsl.exe"))
+            .env("WSLGIT_MOUNT_ROOT", "/mnt")
+            .env("WSLGIT_CD", "false");
+        wslgit
+    }
+
+    /// The arguments the stub was last run with as git.
+    fn args(&self) -> Vec<String> {
+        fs::read_to_string(self.dir.join("args")).unwrap().lines().map(String::from).collect()
+    }
+
+    /// The arguments of each launch of the stub.
+    fn launches(&self) -> Vec<String> {
+        fs::read_to_string(self.dir.join("launches")).unwrap().lines().map(String::from).collect()
+    }
+
+    /// The value of the variable `name` the stub was last run with as git.
+    fn env_var(&self, name: &str) -> Option<String> {
+        let prefix = format!("{}=", name);
+        fs::read_to_string(self.dir.join("env")).unwrap().lines()
+            .find_map(|line| line.strip_prefix(&prefix).map(String::from))
+    }
+}
+
+impl Drop for MockWsl {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+
+#[test]
+fn exit_code_forwarding() {
+    let wsl = MockWsl::new("exit-code");
+    let output = wsl.wslgit(&["status"]).env("MOCK_WSL_EXIT", "0").output().unwrap();
+    assert_eq!(output.status.code(), Some(0));
+    let output = wsl.wslgit(&["merge", "topic"]).env("MOCK_WSL_EXIT", "5").output().unwrap();
+    assert_eq!(output.status.code(), Some(5));
+    assert_eq!(wsl.args(), vec!["git", "merge", "topic"]);
+}
+
+#[test]
+fn argument_translation() {
+    let wsl = MockWsl::new("arguments");
+    let output = wsl.wslgit(&["-C", "C:\\repos\\a b", "add", "D:\\file.txt", "it's"])
+        .output().unwrap();
+    assert!(output.status.success());
+    assert_eq!(wsl.args(), vec!["git", "-C", "'/mnt/c/repos/a b'", "add", "/mnt/d/file.txt",
+                                "'it'\\''s'"]);
+}
+
+#[test]
+fn captured_output_translation() {
+    let wsl = MockWsl::new("output");
+    let output = wsl.wslgit(&["rev-parse", "--show-toplevel"])
+        .env("MOCK_WSL_STDOUT", "/mnt/c/repos/thing\n")
+        .env("MOCK_WSL_STDERR", "fatal: /mnt/c/repos/thing\n")
+        .output().unwrap();
+    assert_eq!(stdout(&output), "c:/repos/thing\n");
+    assert_eq!(String::from_utf8_lossy(&output.stderr), "fatal: c:/repos/thing\n");
+    assert_eq!(wsl.args(), vec!["git", "--no-pager", "rev-parse", "--show-toplevel"]);
+
+    // passed through for other commands
+    let output = wsl.wslgit(&["log"]).env("MOCK_WSL_STDOUT", "/mnt/c/repos/thing\n")
+        .output().unwrap();
+    assert_eq!(stdout(&output), "/mnt/c/repos/thing\n");
+    assert_eq!(wsl.args(), vec!["git", "log"]);
+}
+
+#[test]
+fn editor_environment() {
+    let wsl = MockWsl::new("editor");
+    let output = wsl.wslgit(&["commit"])
+        .env("GIT_EDITOR", "C:\\Tools\\ed.exe --wait")
+        .env("GIT_DIR", "C:\\repos\\thing\\.git")
+        .output().unwrap();
+    assert!(output.status.success());
+    let editor = wsl.env_var("GIT_EDITOR").unwrap();
+    assert!(editor.ends_with(" win-cmd 'C:\\Tools\\ed.exe' --wait"), "{}", editor);
+    assert_eq!(wsl.env_var("GIT_DIR").as_deref(), Some("/mnt/c/repos/thing/.git"));
+    let wsl_env = wsl.env_var("WSLENV").unwrap();
+    assert!(wsl_env.split(':').any(|entry| entry == "GIT_EDITOR/u"), "{}", wsl_env);
+    assert!(wsl_env.split(':').any(|entry| entry == "GIT_DIR/u"), "{}", wsl_env);
+}
+
+#[test]
+fn config_options_with_translated_subcommand() {
+    let wsl = MockWsl::new("config-options");
+    let output = wsl.wslgit(&["-c", "color.ui=never", "-c", "core.hooksPath=C:\\hooks",
+                              "-c", "alias.root=rev-parse --show-toplevel", "rev-parse", "--show-toplevel"])
+        .env("WSLGIT_COLOR", "always")
+        .env("MOCK_WSL_STDOUT", "/mnt/c/repos/thing\n")
+        .output().unwrap();
+    assert_eq!(stdout(&output), "c:/repos/thing\n");
+    // the color of wslgit comes first, so the one of the user wins
+    assert_eq!(wsl.args(), vec!["git", "--no-pager", "-c", "color.ui=always",
+                                "-c", "color.ui=never", "-c", "core.hooksPath=/mnt/c/hooks",
+                                "-c", "'alias.root=rev-parse --show-toplevel'",
+                                "rev-parse", "--show-toplevel"]);
+}
+
+#[test]
+fn repository_config_restrictions() {
+    let wsl = MockWsl::new("repository-config");
+    fs::write(wsl.dir.join(".wslgit"), "cmd = 'sh -c payload'\ngit_bin = '/tmp/payload'\n\
+                                        wsl_args = '--exec /tmp/payload'\nuser = 'root'\n\
+                                        distro = 'Debian'\npath_map = 'C:\\repo=/srv/evil'\n\
+                                        stderr_filter = '^fatal'\n").unwrap();
+    let output = wsl.wslgit(&["add", "C:\\repo\\x"]).output().unwrap();
+    assert!(output.status.success());
+    assert_eq!(wsl.args(), vec!["git", "add", "/mnt/c/repo/x"]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    for setting in &["CMD", "USER", "DISTRO", "PATH_MAP", "STDERR_FILTER"] {
+        assert!(stderr.contains(&format!("ignoring WSLGIT_{} in ", setting)), "{}", stderr);
+    }
+    // git's errors are not hidden by the filter of the repository
+    let output = wsl.wslgit(&["pull"]).env("WSLGIT_SHELL_MODE", "login")
+        .env("MOCK_WSL_STDERR", "fatal: not a git repository\n")
+        .output().unwrap();
+    assert!(String::from_utf8_lossy(&output.stderr).contains("fatal: not a git repository\n"));
+}
+
+#[test]
+fn credential_helper_installation() {
+    let wsl = MockWsl::new("credential-helper");
+    let bin_dir = wsl.dir.join("Git").join("mingw64").join("bin");
+    fs::create_dir_all(&bin_dir).unwrap();
+    let gcm = bin_dir.join("git-credential-manager.exe");
+    fs::write(&gcm, "").unwrap();
+
+    let output = wsl.wslgit(&["install-credential-helper", "--wslgit-dry-run"])
+        .env("ProgramFiles", &wsl.dir)
+        .output().unwrap();
+    assert!(output.status.success());
+    assert!(stdout(&output).starts_with(&format!("credential.helper = {}\n", gcm.display())));
+    assert!(!wsl.dir.join("args").exists());
+
+    let output = wsl.wslgit(&["install-credential-helper"]).env("ProgramFiles", &wsl.dir)
+        .output().unwrap();
+    assert!(output.status.success());
+    assert_eq!(wsl.args(), vec!["git".to_owned(), "config".to_owned(), "--global".to_owned(),
+                                "credential.helper".to_owned(), gcm.display().to_string()]);
+
+    let output = wsl.wslgit(&["install-credential-helper"]).output().unwrap();
+    assert_eq!(output.status.code(), Some(1));
+}
+
+#[test]
+fn working_dir_override() {
+    let wsl = MockWsl::new("working-dir");
+    let repo = wsl.dir.join("repo");
+    fs::create_dir_all(&repo).unwrap();
+    let output = wsl.wslgit(&["status"]).env("WSLGIT_CD", "true").env("WSLGIT_CWD", &repo)
+        .output().unwrap();
+    assert!(output.status.success());
+    assert_eq!(wsl.args(), vec!["--cd".to_owned(), repo.display().to_string(), "git".to_owned(),
+                                "status".to_owned()]);
+    let output = wsl.wslgit(&["status"]).env("WSLGIT_CD", "true").output().unwrap();
+    assert!(output.status.success());
+    assert_eq!(wsl.args()[1], fs::canonicalize(&wsl.dir).unwrap().display().to_string());
+}
+
+#[test]
+fn interactive_login_shell() {
+    let wsl = MockWsl::new("login-shell");
+    let output = wsl.wslgit(&["commit", "-m", "it's done"])
+        .env("WSLGIT_LOGIN_SHELL", "1")
+        .env("MOCK_WSL_STDERR", "bash: cannot set terminal process group (7): Inappropriate ioctl \
+                                 for device\nbash: no job control in this shell\nfatal: no changes\n")
+        .output().unwrap();
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stderr), "fatal: no changes\n");
+    assert_eq!(wsl.args(), vec!["-e", "bash", "-lic", "git commit -m 'it'\\''s done'"]);
+}
+
+#[test]
+fn login_shell_banner_filter() {
+    let wsl = MockWsl::new("banner-filter");
+    let output = wsl.wslgit(&["pull"])
+        .env("WSLGIT_SHELL_MODE", "login")
+        .env("WSLGIT_STDERR_FILTER", "^(Welcome to |Last login: )")
+        .env("MOCK_WSL_STDERR", "Welcome to Ubuntu 22.04.3 LTS (GNU/Linux 5.15.0 x86_64)\n\
+                                 fatal: not a git repository\nLast login: Mon Oct 12 09:14:02 2026\n")
+        .output().unwrap();
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stderr), "fatal: not a git repository\n");
+    assert_eq!(wsl.args(), vec!["-e", "bash", "-lc", "git pull"]);
+}
+
+#[test]
+fn wrapped_command_substitution() {
+    let wsl = MockWsl::new("wrapped-command");
+    let output = wsl.wslgit(&["-rn", "TODO", "C:\\src"]).env("WSLGIT_CMD", "grep --color=never")
+        .output().unwrap();
+    assert!(output.status.success());
+    assert_eq!(wsl.args(), vec!["grep", "--color=never", "-rn", "TODO", "/mnt/c/src"]);
+
+    // no pager option, output translation or version suffix as for git
+    for (args, stdout_text) in &[(["--version"], "GNU Make 4.3\n"), (["config"], "/mnt/c/Makefile\n")] {
+        let output = wsl.wslgit(&args[..]).env("WSLGIT_CMD", "make")
+            .env("MOCK_WSL_STDOUT", stdout_text).output().unwrap();
+        assert!(output.status.success());
+        assert_eq!(stdout(&output), *stdout_text);
+        assert_eq!(wsl.args(), vec!["make", args[0]]);
+    }
+}
+
+#[test]
+fn wsl_launch_failure() {
+    let wsl = MockWsl::new("launch-failure");
+    let output = wsl.wslgit(&["status"]).env("WSLGIT_WSL_EXE", wsl.dir.join("missing.exe"))
+        .output().unwrap();
+    assert_eq!(output.status.code(), Some(127));
+    let errors = String::from_utf8_lossy(&output.stderr).into_owned();
+    assert!(errors.starts_with("wslgit: ") && errors.contains("missing.exe"), "{}", errors);
+
+    let not_executable = wsl.dir.join("not-executable.exe");
+    fs::write(&not_executable, "").unwrap();
+    let output = wsl.wslgit(&["status"]).env("WSLGIT_WSL_EXE", &not_executable).output().unwrap();
+    assert_eq!(output.status.code(), Some(126));
+}
+
+#[test]
+fn wsl_exe_from_environment_only() {
+    let wsl = MockWsl::new("wsl-exe-config");
+    let global_dir = wsl.dir.join("wslgit");
+    fs::create_dir_all(&global_dir).unwrap();
+    fs::write(global_dir.join("config.toml"), "wsl_exe = '/tmp/payload'\n").unwrap();
+    fs::write(wsl.dir.join(".wslgit"), "wsl_exe = '/tmp/payload'\n").unwrap();
+    // the stub is found on the `Path` instead
+    let path = env::join_paths(Some(wsl.dir.clone()).into_iter()
+        .chain(env::split_paths(&env::var_os("PATH").unwrap_or_default()))).unwrap();
+    let output = wsl.wslgit(&["status"]).env_remove("WSLGIT_WSL_EXE").env("PATH", path)
+        .env("APPDATA", &wsl.dir).output().unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(wsl.args(), vec!["git", "status"]);
+}
+
+#[test]
+fn selected_distro_checked_after_failure_only() {
+    let wsl = MockWsl::new("selected-distro");
+    // the stub lists only Ubuntu, so Debian is not checked before running git
+    for distro in &["Ubuntu", "Debian"] {
+        let output = wsl.wslgit(&["status"]).env("WSLGIT_DISTRO", distro).output().unwrap();
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        assert_eq!(wsl.args(), vec!["-d", distro, "git", "status"]);
+    }
+}
+
+#[test]
+fn single_wsl_probe() {
+    let wsl = MockWsl::new("single-probe");
+    let probe = "5.15.90.1-microsoft-standard-WSL2\\n\\0000\
+                 C:\\134 /win/c 9p rw,path=C:\\134 0 0\\nD:\\134 /win/d 9p rw,path=D:\\134 0 0\\n\\0000\
+                 \\0000/home/me\\0000Ubuntu";
+    let output = wsl.wslgit(&["add", "D:\\file.txt"]).env_remove("WSLGIT_MOUNT_ROOT")
+        .env_remove("WSLGIT_CD").env("MOCK_WSL_PROBE", probe).env("WSLGIT_UNC_OUTPUT", "1")
+        .output().unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    // the probe, then git in the directory of WSL 2
+    let launches = wsl.launches();
+    assert_eq!(launches.len(), 2, "{:?}", launches);
+    assert!(launches[0].contains("/proc/mounts"), "{}", launches[0]);
+    assert!(launches[1].starts_with("--cd "), "{}", launches[1]);
+    assert_eq!(&wsl.args()[2..], ["git", "add", "/win/d/file.txt"]);
+}